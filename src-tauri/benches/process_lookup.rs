@@ -0,0 +1,91 @@
+//! Per-event cost of resolving the foreground process and its ancestors.
+//!
+//! Run with `cargo bench --bench process_lookup`. Compares reading the whole
+//! process table on every focus change, as the monitor used to, with the
+//! process cache on a miss (a newly seen PID) and on a hit.
+
+#[path = "../src/process_cache.rs"]
+#[allow(dead_code)]
+mod process_cache;
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+
+const ITERATIONS: u32 = 200;
+// Same limit as the monitor
+const MAX_ANCESTORS: usize = 16;
+
+fn full_refresh(pid: u32) -> usize {
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing()
+            .with_exe(UpdateKind::OnlyIfNotSet)
+            .with_cmd(UpdateKind::OnlyIfNotSet),
+    );
+    let mut depth = 0;
+    let mut process = sys.process(Pid::from_u32(pid));
+    while let Some(p) = process.filter(|_| depth <= MAX_ANCESTORS) {
+        black_box(p.exe());
+        depth += 1;
+        process = p.parent().and_then(|parent| sys.process(parent));
+    }
+    depth
+}
+
+fn cached(pid: u32) -> usize {
+    let mut depth = 0;
+    let mut process = process_cache::lookup(pid);
+    while let Some(p) = process.filter(|_| depth <= MAX_ANCESTORS) {
+        black_box(&p.path);
+        depth += 1;
+        process = p.parent.and_then(process_cache::lookup);
+    }
+    depth
+}
+
+fn ancestry(pid: u32) -> Vec<u32> {
+    let mut pids = Vec::new();
+    let mut current = Some(pid);
+    while let Some(pid) = current.filter(|_| pids.len() <= MAX_ANCESTORS) {
+        pids.push(pid);
+        current = process_cache::lookup(pid).and_then(|p| p.parent);
+    }
+    pids
+}
+
+fn measure(name: &str, mut run: impl FnMut()) {
+    let mut total = Duration::ZERO;
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        run();
+        total += start.elapsed();
+    }
+    println!("{:<28} {:>10.1?} per event", name, total / ITERATIONS);
+}
+
+fn main() {
+    let pid = std::process::id();
+    let chain = ancestry(pid);
+    let processes = System::new_all().processes().len();
+    println!(
+        "{} processes running, foreground chain of {}",
+        processes,
+        chain.len()
+    );
+
+    measure("full table refresh", || {
+        black_box(full_refresh(pid));
+    });
+    measure("cache miss (whole chain)", || {
+        for &pid in &chain {
+            process_cache::invalidate(pid);
+        }
+        black_box(cached(pid));
+    });
+    measure("cache hit", || {
+        black_box(cached(pid));
+    });
+}
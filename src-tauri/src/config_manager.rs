@@ -1,11 +1,15 @@
-use log;
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Mutex;
 use tauri::{AppHandle, Manager};
 
-use crate::resolution_manager::Resolution;
+use crate::matcher::{MatchConditions, MatchSubject, ProcessMatcher, RuleRank};
+use crate::resolution_manager::{
+    self, DisplayError, DisplayTarget, FallbackPolicy, Persistence, Resolution, Rotation, Scaling,
+    Topology,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UiConfig {
@@ -23,17 +27,87 @@ pub struct SystemConfig {
 #[serde(rename_all = "camelCase")]
 pub struct MouseLockConfig {
     pub process: String,
+    /// Rule picking out the process; matches `process` by name when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<ProcessMatcher>,
+    #[serde(flatten)]
+    pub conditions: MatchConditions,
     pub padding_x: u32,
     pub padding_y: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl MouseLockConfig {
+    pub fn matcher(&self) -> ProcessMatcher {
+        ProcessMatcher::for_rule(&self.process, self.matcher.as_ref())
+    }
+
+    pub fn matches(&self, subject: &MatchSubject) -> bool {
+        self.matcher().matches(subject) && self.conditions.matches(subject)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub struct ProfileConfig {
     pub process: String,
+    /// Rule picking out the process; matches `process` by name when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<ProcessMatcher>,
+    #[serde(flatten)]
+    pub conditions: MatchConditions,
+    /// Higher wins when several profiles match; ties go to the more specific
+    /// rule, then to the one listed first.
+    #[serde(default)]
+    pub priority: i32,
+    /// What makes the profile apply.
+    #[serde(default)]
+    pub trigger: Trigger,
     pub width: u32,
     pub height: u32,
     pub frequency: u32,
+    /// Color depth to pin; any depth the driver pairs with the mode when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bits_per_pel: Option<u32>,
+    /// Scaling to switch to along with the mode, e.g. stretched 4:3.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scaling: Option<Scaling>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interlaced: bool,
+    /// Orientation to rotate the display to; width and height stay in the
+    /// panel's native orientation.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<Rotation>,
+    /// Display the profile applies to; the primary display when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayTarget>,
+    /// Mode to use instead when the display does not offer this exact one.
+    #[serde(default)]
+    pub fallback: FallbackPolicy,
+    /// Whether the switch is temporary or also becomes the stored desktop
+    /// mode. Topology profiles are stored by the platform either way.
+    #[serde(default)]
+    pub persistence: Persistence,
+    /// Full display layout to apply instead of the single mode above.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub topology: Option<Topology>,
+}
+
+impl ProfileConfig {
+    pub fn matcher(&self) -> ProcessMatcher {
+        ProcessMatcher::for_rule(&self.process, self.matcher.as_ref())
+    }
+
+    pub fn rank(&self) -> RuleRank {
+        RuleRank {
+            priority: self.priority,
+            specificity: self.matcher().specificity(),
+            conditions: self.conditions.count(),
+        }
+    }
+
+    pub fn matches(&self, subject: &MatchSubject) -> bool {
+        self.matcher().matches(subject) && self.conditions.matches(subject)
+    }
 }
 
 impl From<ProfileConfig> for Resolution {
@@ -42,6 +116,11 @@ impl From<ProfileConfig> for Resolution {
             width: val.width,
             height: val.height,
             frequency: val.frequency,
+            bits_per_pel: val.bits_per_pel,
+            scaling: val.scaling,
+            interlaced: val.interlaced,
+            rotation: val.rotation,
+            display: val.display,
         }
     }
 }
@@ -52,6 +131,39 @@ pub struct AutoResConfig {
     pub revert_delay: u64,
     pub default_profile: Option<Resolution>,
     pub profiles: Vec<ProfileConfig>,
+    #[serde(default)]
+    pub external_change: ExternalChangePolicy,
+}
+
+/// When a profile applies. Every trigger reverts as soon as the matched
+/// process exits.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Trigger {
+    /// While one of the process's windows has focus; reverts after
+    /// `revert_delay` once it loses focus.
+    #[default]
+    WhileFocused,
+    /// From the moment the process runs (also when it was already running at
+    /// startup) until it exits, whatever has focus.
+    WhileRunning,
+    /// Once when the process starts, until it exits. Not applied to processes
+    /// that were already running at startup.
+    OnLaunch,
+}
+
+/// What to do when the display mode is changed by someone else (the user in
+/// the OS settings, or the game itself) while a profile is active.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExternalChangePolicy {
+    /// Still revert to the mode from before the profile.
+    #[default]
+    KeepOriginal,
+    /// The new mode becomes the one to revert to, so it stays.
+    UpdateOriginal,
+    /// Consider the profile done: nothing is reverted for it.
+    ForgetProfile,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -84,6 +196,7 @@ impl Default for AppConfig {
                     revert_delay: 15000,
                     default_profile: None,
                     profiles: Vec::new(),
+                    external_change: ExternalChangePolicy::default(),
                 },
             },
         }
@@ -135,8 +248,170 @@ pub fn get_config(app: AppHandle) -> AppConfig {
     config
 }
 
+/// Pins display targets that only carry a device name (as picked in the UI) to
+/// the monitor currently behind that name, so they keep pointing at the same
+/// screen when device names get reshuffled.
+fn pin_display_targets(config: &mut AppConfig) {
+    let auto_res = &mut config.automation.auto_res;
+    let targets = auto_res
+        .profiles
+        .iter_mut()
+        .filter_map(|p| p.display.as_mut())
+        .chain(
+            auto_res
+                .default_profile
+                .as_mut()
+                .and_then(|r| r.display.as_mut()),
+        );
+
+    for target in targets.filter(|t| t.is_bare()) {
+        match resolution_manager::find_display(target) {
+            Some(display) => *target = display.target(),
+            None => log::warn!("Display not connected: {}", target),
+        }
+    }
+}
+
+/// A problem with a profile found while saving. The config is stored anyway so
+/// the user can fix it later, but the UI gets to flag the profile.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileIssue {
+    /// Process of the offending profile; `None` for the default profile.
+    pub process: Option<String>,
+    /// Position in `profiles`; `None` for the default profile.
+    pub index: Option<usize>,
+    #[serde(flatten)]
+    pub problem: ProfileProblem,
+}
+
+/// Serialized as `{ "problem": "shadowed", "by": 2 }` next to the profile.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "problem", rename_all = "camelCase")]
+pub enum ProfileProblem {
+    /// The driver would refuse the profile's mode.
+    Rejected { error: DisplayError },
+    /// Same matcher and conditions as the profile at index `of`, which wins.
+    Duplicate { of: usize },
+    /// The profile at index `by` matches everything this one does and wins.
+    Shadowed { by: usize },
+}
+
+/// Dry-runs every profile's mode (after its fallback policy) against the
+/// driver. Topology profiles are checked when they are applied, since their
+/// outputs depend on each other.
+fn validate_profiles(config: &AppConfig) -> Vec<ProfileIssue> {
+    let auto_res = &config.automation.auto_res;
+    let profiles = auto_res
+        .profiles
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.topology.is_none())
+        .map(|(index, p)| {
+            (
+                Some(p.process.clone()),
+                Some(index),
+                Resolution::from(p.clone()),
+                p.fallback,
+            )
+        });
+    let default = auto_res
+        .default_profile
+        .clone()
+        .map(|res| (None, None, res, FallbackPolicy::Exact));
+
+    profiles
+        .chain(default)
+        .filter_map(|(process, index, res, fallback)| {
+            let error = resolution_manager::resolve_mode(&res, fallback)
+                .and_then(|mode| resolution_manager::test_resolution(&mode))
+                .err()?;
+            log::warn!(
+                "Profile {} ({}x{}@{}) would be rejected: {}",
+                process.as_deref().unwrap_or("default"),
+                res.width,
+                res.height,
+                res.frequency,
+                error
+            );
+            Some(ProfileIssue {
+                process,
+                index,
+                problem: ProfileProblem::Rejected { error },
+            })
+        })
+        .collect()
+}
+
+/// Finds profiles that can never be picked: another profile matches every
+/// process they match (as far as the rules tell) and always outranks them.
+fn find_rule_conflicts(profiles: &[ProfileConfig]) -> Vec<ProfileIssue> {
+    profiles
+        .iter()
+        .enumerate()
+        .filter_map(|(index, profile)| {
+            let (winner, other) = profiles.iter().enumerate().find(|(i, other)| {
+                *i != index
+                    && other.matcher().covers(&profile.matcher())
+                    && other.conditions.covers(&profile.conditions)
+                    && (other.rank(), Reverse(*i)) > (profile.rank(), Reverse(index))
+            })?;
+            let problem =
+                if other.matcher() == profile.matcher() && other.conditions == profile.conditions {
+                    ProfileProblem::Duplicate { of: winner }
+                } else {
+                    ProfileProblem::Shadowed { by: winner }
+                };
+            log::warn!(
+                "Profile {} can never apply, {} always wins over it",
+                profile.process,
+                other.process
+            );
+            Some(ProfileIssue {
+                process: Some(profile.process.clone()),
+                index: Some(index),
+                problem,
+            })
+        })
+        .collect()
+}
+
+/// The profile to apply for `subject`: the highest ranked one that matches,
+/// and the first listed among equally ranked ones.
+pub fn best_profile<'a>(
+    profiles: &'a [ProfileConfig],
+    subject: &MatchSubject,
+) -> Option<(usize, &'a ProfileConfig)> {
+    profiles
+        .iter()
+        .enumerate()
+        .filter(|(_, p)| p.matches(subject))
+        .max_by_key(|(i, p)| (p.rank(), Reverse(*i)))
+}
+
+/// A rule whose pattern does not compile would never match, so the config is
+/// not saved until it is fixed.
+fn validate_matchers(config: &AppConfig) -> Result<(), String> {
+    let profiles = config.automation.auto_res.profiles.iter();
+    let locks = config.automation.mouse_lock.iter();
+    profiles
+        .map(|p| (&p.process, p.matcher(), &p.conditions))
+        .chain(locks.map(|l| (&l.process, l.matcher(), &l.conditions)))
+        .try_for_each(|(process, matcher, conditions)| {
+            matcher
+                .validate()
+                .and_then(|_| conditions.validate())
+                .map_err(|e| format!("Rule for {}: {}", process, e))
+        })
+}
+
 #[tauri::command]
-pub fn save_config(app: AppHandle, config: AppConfig) -> Result<(), String> {
+pub fn save_config(app: AppHandle, mut config: AppConfig) -> Result<Vec<ProfileIssue>, String> {
+    validate_matchers(&config)?;
+    pin_display_targets(&mut config);
+    let mut issues = find_rule_conflicts(&config.automation.auto_res.profiles);
+    issues.extend(validate_profiles(&config));
+
     // 1. Update in-memory cache immediately
     if let Ok(mut guard) = CACHED_CONFIG.lock() {
         *guard = Some(config.clone());
@@ -149,5 +424,123 @@ pub fn save_config(app: AppHandle, config: AppConfig) -> Result<(), String> {
     }
     let json = serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?;
     fs::write(path, json).map_err(|e| e.to_string())?;
-    Ok(())
+    Ok(issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(rule: serde_json::Value) -> ProfileConfig {
+        let mut value = serde_json::json!({ "width": 1920, "height": 1080, "frequency": 60 });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(rule.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn game() -> MatchSubject {
+        MatchSubject {
+            name: "game.exe".to_string(),
+            path: Some(PathBuf::from("/games/game.exe")),
+            title: "Main Menu".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn problems(profiles: &[ProfileConfig]) -> Vec<(Option<usize>, String)> {
+        find_rule_conflicts(profiles)
+            .into_iter()
+            .map(|issue| (issue.index, format!("{:?}", issue.problem)))
+            .collect()
+    }
+
+    #[test]
+    fn conflicts_report_duplicates_of_an_earlier_profile() {
+        let profiles = [
+            profile(serde_json::json!({ "process": "game" })),
+            profile(serde_json::json!({ "process": "game", "width": 1280, "height": 720 })),
+        ];
+        assert_eq!(
+            problems(&profiles),
+            [(Some(1), "Duplicate { of: 0 }".into())]
+        );
+    }
+
+    #[test]
+    fn conflicts_report_profiles_a_broader_one_outranks() {
+        let profiles = [
+            profile(serde_json::json!({ "process": "game", "priority": 1 })),
+            profile(serde_json::json!({
+                "process": "game",
+                "matcher": { "kind": "path", "path": "/games/game.exe" }
+            })),
+        ];
+        assert_eq!(
+            problems(&profiles),
+            [(Some(1), "Shadowed { by: 0 }".into())]
+        );
+    }
+
+    #[test]
+    fn conflicts_leave_narrower_or_more_specific_profiles_alone() {
+        let profiles = [
+            profile(serde_json::json!({ "process": "game" })),
+            profile(serde_json::json!({
+                "process": "game",
+                "windowTitle": { "kind": "contains", "text": "menu" }
+            })),
+            profile(serde_json::json!({
+                "process": "game",
+                "matcher": { "kind": "path", "path": "/games/game.exe" }
+            })),
+        ];
+        assert!(problems(&profiles).is_empty());
+    }
+
+    #[test]
+    fn best_profile_prefers_the_more_specific_rule() {
+        let profiles = [
+            profile(serde_json::json!({ "process": "game" })),
+            profile(serde_json::json!({
+                "process": "game",
+                "matcher": { "kind": "pathPrefix", "path": "/games" }
+            })),
+            profile(serde_json::json!({
+                "process": "game",
+                "windowTitle": { "kind": "contains", "text": "menu" }
+            })),
+        ];
+        assert_eq!(best_profile(&profiles, &game()).map(|(i, _)| i), Some(1));
+        assert!(profiles[1].rank() > profiles[2].rank());
+        assert!(profiles[2].rank() > profiles[0].rank());
+    }
+
+    #[test]
+    fn best_profile_lets_priority_win_and_ties_go_to_the_first() {
+        let mut profiles = vec![
+            profile(serde_json::json!({ "process": "game" })),
+            profile(serde_json::json!({ "process": "game.exe" })),
+            profile(serde_json::json!({
+                "process": "game",
+                "matcher": { "kind": "path", "path": "/games/game.exe" }
+            })),
+        ];
+        assert_eq!(best_profile(&profiles, &game()).map(|(i, _)| i), Some(2));
+        profiles[1].priority = 1;
+        assert_eq!(best_profile(&profiles, &game()).map(|(i, _)| i), Some(1));
+        profiles.truncate(2);
+        profiles[1].priority = 0;
+        assert_eq!(best_profile(&profiles, &game()).map(|(i, _)| i), Some(0));
+    }
+
+    #[test]
+    fn best_profile_skips_profiles_that_do_not_match() {
+        let profiles = [profile(serde_json::json!({
+            "process": "game",
+            "windowTitle": { "kind": "contains", "text": "level" }
+        }))];
+        assert_eq!(best_profile(&profiles, &game()), None);
+    }
 }
@@ -0,0 +1,147 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use crate::resolution_manager::{self, DisplayError, Persistence, Resolution};
+
+// Seconds to wait for confirm_resolution when the caller does not say
+pub const DEFAULT_TIMEOUT_SECS: u64 = 15;
+
+/// A manual change waiting for the user to keep it, like the "Keep these
+/// display settings?" dialog of the OS.
+struct PendingChange {
+    id: u64,
+    /// Modes to go back to, one per display changed since the last confirmation.
+    previous: Vec<Resolution>,
+    persistence: Persistence,
+}
+
+static PENDING: Mutex<Option<PendingChange>> = Mutex::new(None);
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Applies `res` and starts the rollback timer. Unless `confirm` is called
+/// within `timeout_secs`, the displays go back to their modes from before the
+/// first unconfirmed change.
+pub fn apply_with_confirmation(
+    app: &AppHandle,
+    res: Resolution,
+    persistence: Persistence,
+    timeout_secs: u64,
+) -> Result<(), DisplayError> {
+    // Captured against the resolved display, so a change addressed to the
+    // primary display and one naming the same screen count as one display.
+    let display = resolution_manager::resolve_display(res.display.as_ref());
+    let previous = display.as_ref().and_then(|display| {
+        resolution_manager::get_current_resolution(Some(&display.target()))
+            .map(|mode| (display.device_name.clone(), mode))
+    });
+    resolution_manager::change_resolution(res.clone(), persistence)?;
+
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    {
+        let mut pending = PENDING.lock().unwrap();
+        let mut previous_modes = pending.take().map(|p| p.previous).unwrap_or_default();
+        if let Some((device, previous)) = previous {
+            if !previous_modes
+                .iter()
+                .any(|p| p.device_name() == Some(&device))
+            {
+                previous_modes.push(previous);
+            }
+        }
+        *pending = Some(PendingChange {
+            id,
+            previous: previous_modes,
+            persistence,
+        });
+    }
+
+    log::info!(
+        "Applied {}x{}@{}, reverting in {}s unless confirmed",
+        res.width,
+        res.height,
+        res.frequency,
+        timeout_secs
+    );
+    let _ = app.emit(
+        "resolution-changed",
+        serde_json::json!({
+            "resolution": format!("{}x{}@{}Hz", res.width, res.height, res.frequency),
+            "timeout": timeout_secs,
+            "status": "confirm-pending"
+        }),
+    );
+
+    let app = app.clone();
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_secs(timeout_secs));
+        // A newer change restarts the countdown with its own timer.
+        if let Some(pending) = take_pending(Some(id)) {
+            log::info!("Display change was not confirmed, rolling back");
+            roll_back(&app, &pending);
+        }
+    });
+
+    Ok(())
+}
+
+/// Keeps the pending change. Returns false when nothing was waiting.
+pub fn confirm(app: &AppHandle) -> bool {
+    if take_pending(None).is_none() {
+        return false;
+    }
+    log::info!("Display change confirmed");
+    let _ = app.emit(
+        "resolution-changed",
+        serde_json::json!({ "status": "confirmed" }),
+    );
+    true
+}
+
+/// Rolls the pending change back right away instead of waiting for the timer.
+/// Returns false when nothing was waiting.
+pub fn revert(app: &AppHandle) -> bool {
+    match take_pending(None) {
+        Some(pending) => {
+            roll_back(app, &pending);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Rolls back an unconfirmed change when the app exits; its timer would
+/// never fire.
+pub fn shutdown(app: &AppHandle) {
+    if let Some(pending) = take_pending(None) {
+        log::info!("Shutting down with an unconfirmed display change, rolling back");
+        roll_back(app, &pending);
+    }
+}
+
+/// Removes the pending change, but only if it is `id` when one is given.
+fn take_pending(id: Option<u64>) -> Option<PendingChange> {
+    let mut pending = PENDING.lock().unwrap();
+    match (pending.as_ref(), id) {
+        (Some(p), Some(id)) if p.id != id => None,
+        _ => pending.take(),
+    }
+}
+
+fn roll_back(app: &AppHandle, pending: &PendingChange) {
+    let mut error = None;
+    for res in &pending.previous {
+        if let Err(e) = resolution_manager::change_resolution(res.clone(), pending.persistence) {
+            log::error!("Failed to roll back display change: {}", e);
+            error = Some(e);
+        }
+    }
+    let _ = app.emit(
+        "resolution-changed",
+        match error {
+            None => serde_json::json!({ "status": "rolled-back" }),
+            Some(e) => serde_json::json!({ "status": "failed", "error": e }),
+        },
+    );
+}
@@ -0,0 +1,82 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::time::Duration;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "linux")]
+mod x11;
+
+/// Something about the attached displays changed outside of a single call
+/// into the backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayEvent {
+    /// A mode, rotation or layout changed, by us or anyone else.
+    ModeChanged,
+    /// A monitor was connected, disconnected, or turned on or off.
+    DevicesChanged,
+}
+
+pub type DisplayEventHandler = Box<dyn FnMut(DisplayEvent) + Send>;
+
+// Changes arrive in bursts (one per output, plus the desktop resize), so events
+// within this window are reported once.
+const SETTLE_DELAY: Duration = Duration::from_millis(500);
+
+/// Starts listening for display notifications: `WM_DISPLAYCHANGE` and
+/// `WM_DEVICECHANGE` on Windows, RandR `RRScreenChangeNotify` and
+/// `RROutputChangeNotify` on X11. `handler` runs on its own thread, never
+/// on the one receiving the notification: Windows sends `WM_DISPLAYCHANGE`
+/// synchronously from inside `ChangeDisplaySettingsEx`, so handling it in place
+/// could wait on a lock held by the thread changing the mode.
+pub fn watch(handler: DisplayEventHandler) {
+    let (tx, rx) = mpsc::channel();
+    if !listen(tx) {
+        log::warn!("Display change notifications are unavailable on this session");
+        return;
+    }
+
+    std::thread::spawn(move || {
+        println!("Starting Display Event Thread...");
+        dispatch(rx, handler);
+    });
+}
+
+fn dispatch(rx: Receiver<DisplayEvent>, mut handler: DisplayEventHandler) {
+    while let Ok(event) = rx.recv() {
+        std::thread::sleep(SETTLE_DELAY);
+        let mut events = vec![event];
+        for event in rx.try_iter() {
+            if !events.contains(&event) {
+                events.push(event);
+            }
+        }
+        for event in events {
+            handler(event);
+        }
+    }
+}
+
+fn listen(tx: Sender<DisplayEvent>) -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        win32::listen(tx);
+        true
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        match x11::listen(tx) {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("RandR notifications unavailable: {}", e);
+                false
+            }
+        }
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        drop(tx);
+        false
+    }
+}
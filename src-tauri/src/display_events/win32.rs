@@ -0,0 +1,59 @@
+use std::cell::RefCell;
+use std::sync::mpsc::Sender;
+
+use windows::core::w;
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+    UI::WindowsAndMessaging::{DefWindowProcW, WM_DEVICECHANGE, WM_DISPLAYCHANGE},
+};
+
+use super::DisplayEvent;
+use crate::hidden_window;
+
+// From dbt.h
+const DBT_DEVNODES_CHANGED: usize = 0x0007;
+
+thread_local! {
+    // The window procedure runs on the thread that created the window.
+    static SENDER: RefCell<Option<Sender<DisplayEvent>>> = const { RefCell::new(None) };
+}
+
+fn send(event: DisplayEvent) {
+    SENDER.with(|sender| {
+        if let Some(sender) = sender.borrow().as_ref() {
+            let _ = sender.send(event);
+        }
+    });
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        WM_DISPLAYCHANGE => {
+            send(DisplayEvent::ModeChanged);
+            LRESULT(0)
+        }
+        // Broadcast as DBT_DEVNODES_CHANGED to every top-level window whenever a
+        // device comes or goes; which displays changed is worked out by the handler.
+        WM_DEVICECHANGE if wparam.0 == DBT_DEVNODES_CHANGED => {
+            send(DisplayEvent::DevicesChanged);
+            LRESULT(1)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// `WM_DISPLAYCHANGE` and `WM_DEVICECHANGE` are broadcast to top-level windows only, so the messages
+/// are received by a hidden one on its own thread.
+pub fn listen(tx: Sender<DisplayEvent>) {
+    std::thread::spawn(move || {
+        println!("Starting Display Change Window Thread...");
+        SENDER.with(|s| *s.borrow_mut() = Some(tx));
+
+        hidden_window::run(w!("KortexDisplayWatcher"), window_proc, "display changes");
+    });
+}
@@ -0,0 +1,47 @@
+use std::sync::mpsc::Sender;
+
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::{ConnectionExt as _, Notify, NotifyMask};
+use x11rb::protocol::Event;
+
+use super::DisplayEvent;
+
+/// Selects RandR screen-change and output-change events on the root window of a dedicated
+/// connection and forwards them from a background thread.
+pub fn listen(tx: Sender<DisplayEvent>) -> Result<(), String> {
+    let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+    let root = conn.setup().roots[screen_num].root;
+    conn.randr_query_version(1, 2)
+        .map_err(|e| e.to_string())?
+        .reply()
+        .map_err(|e| e.to_string())?;
+    conn.randr_select_input(root, NotifyMask::SCREEN_CHANGE | NotifyMask::OUTPUT_CHANGE)
+        .map_err(|e| e.to_string())?
+        .check()
+        .map_err(|e| e.to_string())?;
+
+    std::thread::spawn(move || {
+        println!("Starting RandR Listener Thread...");
+        loop {
+            let event = match conn.wait_for_event() {
+                Ok(event) => event,
+                Err(e) => {
+                    eprintln!("RandR listener stopped: {}", e);
+                    return;
+                }
+            };
+            let display_event = match event {
+                Event::RandrScreenChangeNotify(_) => DisplayEvent::ModeChanged,
+                // Sent per output when its connection state or CRTC changes.
+                Event::RandrNotify(notify) if notify.sub_code == Notify::OUTPUT_CHANGE => {
+                    DisplayEvent::DevicesChanged
+                }
+                _ => continue,
+            };
+            if tx.send(display_event).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(())
+}
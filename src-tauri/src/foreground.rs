@@ -0,0 +1,109 @@
+use std::sync::OnceLock;
+
+#[cfg(target_os = "windows")]
+mod win32;
+#[cfg(target_os = "linux")]
+mod x11;
+
+#[cfg(target_os = "windows")]
+pub use win32::Win32Source;
+#[cfg(target_os = "linux")]
+pub use x11::X11Source;
+
+/// Opaque window handle (an `HWND` on Windows, an X11 window id on Linux).
+pub type WindowId = usize;
+
+/// Screen-space window bounds, edges exclusive like a Win32 `RECT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowRect {
+    pub left: i32,
+    pub top: i32,
+    pub right: i32,
+    pub bottom: i32,
+}
+
+/// A window became the foreground window, or the foreground window changed
+/// its title or size.
+#[derive(Debug, Clone)]
+pub struct ForegroundEvent {
+    pub window: WindowId,
+    pub pid: u32,
+    pub title: String,
+    pub class: String,
+}
+
+pub type ForegroundHandler = Box<dyn FnMut(ForegroundEvent) + Send>;
+
+/// Platform layer reporting which window has focus.
+///
+/// The process monitor only sees `ForegroundEvent`s, so the same matching and
+/// revert logic drives every platform that has a source.
+pub trait ForegroundSource: Send + Sync {
+    /// Short identifier used in logs, e.g. "win32".
+    fn name(&self) -> &'static str;
+
+    /// Blocks the calling thread, invoking `handler` for every foreground change
+    /// (starting with the current foreground window) and every title or size
+    /// change of the foreground window, until the source fails.
+    fn run(&self, handler: ForegroundHandler) -> Result<(), String>;
+
+    /// Current bounds of `window`, or `None` if it no longer exists.
+    fn window_rect(&self, window: WindowId) -> Option<WindowRect>;
+
+    /// Bounds of the monitor `window` is on (the nearest one if it is off
+    /// screen), or `None` if unknown.
+    fn monitor_rect(&self, _window: WindowId) -> Option<WindowRect> {
+        None
+    }
+
+    /// Whether `window` has no title bar or resizable frame.
+    fn is_borderless(&self, _window: WindowId) -> bool {
+        false
+    }
+
+    /// Confine the cursor to `rect`, or release it with `None`.
+    /// Returns `false` if the platform cannot confine the cursor.
+    fn confine_cursor(&self, _rect: Option<WindowRect>) -> bool {
+        false
+    }
+}
+
+static SOURCE: OnceLock<Option<Box<dyn ForegroundSource>>> = OnceLock::new();
+
+/// Returns the foreground source for this session, selecting it on first use.
+/// `None` means focus changes cannot be observed (e.g. a pure Wayland session).
+pub fn source() -> Option<&'static dyn ForegroundSource> {
+    SOURCE
+        .get_or_init(|| {
+            let source = select_source();
+            match &source {
+                Some(source) => log::info!("Using foreground source: {}", source.name()),
+                None => log::warn!("No foreground source available on this session"),
+            }
+            source
+        })
+        .as_deref()
+}
+
+fn select_source() -> Option<Box<dyn ForegroundSource>> {
+    #[cfg(target_os = "windows")]
+    {
+        Some(Box::new(Win32Source))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        match X11Source::connect() {
+            Ok(source) => Some(Box::new(source)),
+            Err(e) => {
+                log::warn!("X11 foreground source unavailable: {}", e);
+                None
+            }
+        }
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        None
+    }
+}
@@ -0,0 +1,227 @@
+use std::cell::{Cell, RefCell};
+use std::sync::mpsc::{self, Sender};
+
+use windows::Win32::{
+    Foundation::{HWND, RECT},
+    Graphics::Gdi::{GetMonitorInfoW, MonitorFromWindow, MONITORINFO, MONITOR_DEFAULTTONEAREST},
+    UI::{
+        Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK},
+        WindowsAndMessaging::{
+            ClipCursor, DispatchMessageW, GetClassNameW, GetForegroundWindow, GetMessageW,
+            GetWindowLongW, GetWindowRect, GetWindowThreadProcessId, InternalGetWindowText,
+            IsIconic, TranslateMessage, CHILDID_SELF, EVENT_OBJECT_LOCATIONCHANGE,
+            EVENT_OBJECT_NAMECHANGE, EVENT_SYSTEM_FOREGROUND, GWL_STYLE, MSG, OBJID_WINDOW,
+            WINEVENT_OUTOFCONTEXT, WS_CAPTION, WS_THICKFRAME,
+        },
+    },
+};
+
+use super::{ForegroundEvent, ForegroundHandler, ForegroundSource, WindowId, WindowRect};
+
+/// Foreground tracking via `SetWinEventHook(EVENT_SYSTEM_FOREGROUND)`.
+pub struct Win32Source;
+
+thread_local! {
+    // Out-of-context hooks are delivered on the thread that installed them.
+    // They only queue events: a mode change made by the handler resizes the
+    // foreground window, and the resulting hook call must not run the handler
+    // again while it is still busy.
+    static EVENTS: RefCell<Option<Sender<ForegroundEvent>>> = const { RefCell::new(None) };
+    // Window and size last reported, so moves alone do not re-run the rules.
+    static LAST_SIZE: Cell<(isize, i32, i32)> = const { Cell::new((0, 0, 0)) };
+}
+
+// Each hooked on its own; a single range would take in every event in between.
+const HOOKED_EVENTS: [u32; 3] = [
+    EVENT_SYSTEM_FOREGROUND,
+    EVENT_OBJECT_NAMECHANGE,
+    EVENT_OBJECT_LOCATIONCHANGE,
+];
+
+// Hook Callback
+unsafe extern "system" fn win_event_hook(
+    _h_win_event_hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    id_object: i32,
+    id_child: i32,
+    _id_event_thread: u32,
+    _dw_ms_event_time: u32,
+) {
+    if event == EVENT_SYSTEM_FOREGROUND {
+        dispatch(hwnd);
+        return;
+    }
+    // Name and location changes arrive for every object on the desktop (each
+    // caret blink and cursor move); only the foreground window itself counts.
+    if id_object != OBJID_WINDOW.0 || id_child != CHILDID_SELF as i32 {
+        return;
+    }
+    if hwnd != GetForegroundWindow() {
+        return;
+    }
+    // The foreground window was retitled (an emulator loaded a game) or
+    // resized (a game left its launcher window for fullscreen).
+    if event == EVENT_OBJECT_NAMECHANGE
+        || (event == EVENT_OBJECT_LOCATIONCHANGE && LAST_SIZE.get() != window_size(hwnd))
+    {
+        dispatch(hwnd);
+    }
+}
+
+fn window_size(hwnd: HWND) -> (isize, i32, i32) {
+    let mut rect = RECT::default();
+    let _ = unsafe { GetWindowRect(hwnd, &mut rect) };
+    (
+        hwnd.0 as isize,
+        rect.right - rect.left,
+        rect.bottom - rect.top,
+    )
+}
+
+fn dispatch(hwnd: HWND) {
+    LAST_SIZE.set(window_size(hwnd));
+    if let Some(event) = describe_window(hwnd) {
+        EVENTS.with(|events| {
+            if let Some(events) = events.borrow().as_ref() {
+                let _ = events.send(event);
+            }
+        });
+    }
+}
+
+fn describe_window(hwnd: HWND) -> Option<ForegroundEvent> {
+    if hwnd.0.is_null() {
+        return None;
+    }
+
+    unsafe {
+        if IsIconic(hwnd).as_bool() {
+            return None;
+        }
+
+        let mut pid = 0;
+        GetWindowThreadProcessId(hwnd, Some(&mut pid));
+
+        // GetWindowTextW sends WM_GETTEXT, which waits on a hung window.
+        let mut buf = [0u16; 512];
+        let len = InternalGetWindowText(hwnd, &mut buf).max(0) as usize;
+        let title = String::from_utf16_lossy(&buf[..len]);
+
+        let len = GetClassNameW(hwnd, &mut buf).max(0) as usize;
+        let class = String::from_utf16_lossy(&buf[..len]);
+
+        Some(ForegroundEvent {
+            window: hwnd.0 as WindowId,
+            pid,
+            title,
+            class,
+        })
+    }
+}
+
+fn to_rect(rect: WindowRect) -> RECT {
+    RECT {
+        left: rect.left,
+        top: rect.top,
+        right: rect.right,
+        bottom: rect.bottom,
+    }
+}
+
+impl ForegroundSource for Win32Source {
+    fn name(&self) -> &'static str {
+        "win32"
+    }
+
+    fn run(&self, mut handler: ForegroundHandler) -> Result<(), String> {
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            println!("Starting Foreground Event Thread...");
+            for event in rx {
+                handler(event);
+            }
+        });
+        EVENTS.with(|events| *events.borrow_mut() = Some(tx));
+
+        unsafe {
+            let hooks: Vec<HWINEVENTHOOK> = HOOKED_EVENTS
+                .iter()
+                .map(|&event| {
+                    SetWinEventHook(
+                        event,
+                        event,
+                        None,
+                        Some(win_event_hook),
+                        0,
+                        0,
+                        WINEVENT_OUTOFCONTEXT,
+                    )
+                })
+                .collect();
+
+            if hooks[0].0.is_null() {
+                EVENTS.with(|events| *events.borrow_mut() = None);
+                return Err("Failed to set SetWinEventHook".to_string());
+            }
+            if hooks.iter().any(|hook| hook.0.is_null()) {
+                log::warn!("Failed to hook window title or size changes");
+            }
+
+            dispatch(GetForegroundWindow());
+
+            let mut msg = MSG::default();
+            while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                let _ = TranslateMessage(&msg);
+                let _ = DispatchMessageW(&msg);
+            }
+
+            for hook in hooks.into_iter().filter(|hook| !hook.0.is_null()) {
+                let _ = UnhookWinEvent(hook);
+            }
+        }
+
+        // Ends the event thread once it has handled what is queued.
+        EVENTS.with(|events| *events.borrow_mut() = None);
+        Ok(())
+    }
+
+    fn window_rect(&self, window: WindowId) -> Option<WindowRect> {
+        let mut rect = RECT::default();
+        unsafe { GetWindowRect(HWND(window as *mut _), &mut rect) }.ok()?;
+        Some(WindowRect {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        })
+    }
+
+    fn monitor_rect(&self, window: WindowId) -> Option<WindowRect> {
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        unsafe {
+            let monitor = MonitorFromWindow(HWND(window as *mut _), MONITOR_DEFAULTTONEAREST);
+            GetMonitorInfoW(monitor, &mut info).ok().ok()?;
+        }
+        let rect = info.rcMonitor;
+        Some(WindowRect {
+            left: rect.left,
+            top: rect.top,
+            right: rect.right,
+            bottom: rect.bottom,
+        })
+    }
+
+    fn is_borderless(&self, window: WindowId) -> bool {
+        let style = unsafe { GetWindowLongW(HWND(window as *mut _), GWL_STYLE) } as u32;
+        style & (WS_CAPTION.0 | WS_THICKFRAME.0) == 0
+    }
+
+    fn confine_cursor(&self, rect: Option<WindowRect>) -> bool {
+        let rect = rect.map(to_rect);
+        unsafe { ClipCursor(rect.as_ref().map(|r| r as *const RECT)) }.is_ok()
+    }
+}
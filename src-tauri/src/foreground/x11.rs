@@ -0,0 +1,405 @@
+use std::sync::Mutex;
+
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::ConnectionExt as _;
+use x11rb::protocol::xfixes::{self, BarrierDirections, ConnectionExt as _};
+use x11rb::protocol::xproto::{
+    AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, EventMask, Window,
+};
+use x11rb::protocol::Event;
+use x11rb::rust_connection::RustConnection;
+
+use super::{ForegroundEvent, ForegroundHandler, ForegroundSource, WindowId, WindowRect};
+
+x11rb::atom_manager! {
+    Atoms: AtomsCookie {
+        _NET_ACTIVE_WINDOW,
+        _NET_WM_PID,
+        _NET_WM_NAME,
+        _NET_WM_STATE,
+        _NET_WM_STATE_HIDDEN,
+        _NET_WM_STATE_FULLSCREEN,
+        _MOTIF_WM_HINTS,
+        UTF8_STRING,
+    }
+}
+
+/// Foreground tracking for EWMH window managers: watches `_NET_ACTIVE_WINDOW`
+/// on the root window via `PropertyNotify`.
+///
+/// The cursor is confined with XFixes pointer barriers (XFixes 5), which
+/// unlike a pointer grab leave the game free to grab the pointer itself.
+pub struct X11Source {
+    conn: RustConnection,
+    root: Window,
+    atoms: Atoms,
+    barriers_supported: bool,
+    /// Rect the cursor is confined to and the barriers around it.
+    confinement: Mutex<Option<(WindowRect, Vec<xfixes::Barrier>)>>,
+}
+
+impl X11Source {
+    pub fn connect() -> Result<Self, String> {
+        let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+        let root = conn.setup().roots[screen_num].root;
+        let atoms = Atoms::new(&conn)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?;
+
+        // Barriers need XFixes 5, and every client has to announce its version.
+        let barriers_supported = conn
+            .xfixes_query_version(5, 0)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .is_some_and(|version| version.major_version >= 5);
+
+        Ok(X11Source {
+            conn,
+            root,
+            atoms,
+            barriers_supported,
+            confinement: Mutex::new(None),
+        })
+    }
+
+    fn active_window(&self) -> Option<Window> {
+        let reply = self
+            .conn
+            .get_property(
+                false,
+                self.root,
+                self.atoms._NET_ACTIVE_WINDOW,
+                AtomEnum::WINDOW,
+                0,
+                1,
+            )
+            .ok()?
+            .reply()
+            .ok()?;
+        reply
+            .value32()
+            .and_then(|mut v| v.next())
+            .filter(|w| *w != x11rb::NONE)
+    }
+
+    fn property(&self, window: Window, property: u32, type_: u32) -> Option<Vec<u8>> {
+        let reply = self
+            .conn
+            .get_property(false, window, property, type_, 0, u32::MAX / 4)
+            .ok()?
+            .reply()
+            .ok()?;
+        if reply.type_ == x11rb::NONE {
+            None
+        } else {
+            Some(reply.value)
+        }
+    }
+
+    fn has_state(&self, window: Window, state: u32) -> bool {
+        self.property(window, self.atoms._NET_WM_STATE, AtomEnum::ATOM.into())
+            .is_some_and(|raw| {
+                raw.chunks_exact(4)
+                    .any(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]) == state)
+            })
+    }
+
+    fn is_hidden(&self, window: Window) -> bool {
+        self.has_state(window, self.atoms._NET_WM_STATE_HIDDEN)
+    }
+
+    /// Subscribes to (or drops) property and geometry changes of a client
+    /// window, so title, state and size changes of the active window are seen.
+    /// Errors for windows that are already gone arrive as events and are
+    /// ignored by the loop.
+    fn watch_window(&self, window: Window, watch: bool) {
+        let mask = if watch {
+            EventMask::PROPERTY_CHANGE | EventMask::STRUCTURE_NOTIFY
+        } else {
+            EventMask::NO_EVENT
+        };
+        let attributes = ChangeWindowAttributesAux::new().event_mask(mask);
+        let _ = self.conn.change_window_attributes(window, &attributes);
+        let _ = self.conn.flush();
+    }
+
+    /// Four barriers along the edges of `rect`, each passable only inwards,
+    /// so a cursor that starts outside can still get in.
+    fn create_barriers(&self, rect: WindowRect) -> Result<Vec<xfixes::Barrier>, String> {
+        let (left, top) = (rect.left.max(0) as u16, rect.top.max(0) as u16);
+        let (right, bottom) = (rect.right.max(0) as u16, rect.bottom.max(0) as u16);
+        let edges = [
+            (left, top, left, bottom, BarrierDirections::POSITIVE_X),
+            (right, top, right, bottom, BarrierDirections::NEGATIVE_X),
+            (left, top, right, top, BarrierDirections::POSITIVE_Y),
+            (left, bottom, right, bottom, BarrierDirections::NEGATIVE_Y),
+        ];
+
+        let mut barriers = Vec::new();
+        for (x1, y1, x2, y2, allowed) in edges {
+            let created = self
+                .conn
+                .generate_id()
+                .map_err(|e| e.to_string())
+                .and_then(|barrier| {
+                    self.conn
+                        .xfixes_create_pointer_barrier(
+                            barrier,
+                            self.root,
+                            x1,
+                            y1,
+                            x2,
+                            y2,
+                            allowed,
+                            &[],
+                        )
+                        .map(|_| barrier)
+                        .map_err(|e| e.to_string())
+                });
+            match created {
+                Ok(barrier) => barriers.push(barrier),
+                Err(e) => {
+                    self.delete_barriers(barriers);
+                    return Err(e);
+                }
+            }
+        }
+        self.conn.flush().map_err(|e| e.to_string())?;
+        Ok(barriers)
+    }
+
+    fn delete_barriers(&self, barriers: Vec<xfixes::Barrier>) {
+        for barrier in barriers {
+            let _ = self.conn.xfixes_delete_pointer_barrier(barrier);
+        }
+        let _ = self.conn.flush();
+    }
+
+    fn window_size(&self, window: Window) -> Option<(i32, i32)> {
+        let rect = self.window_rect(window as WindowId)?;
+        Some((rect.right - rect.left, rect.bottom - rect.top))
+    }
+
+    fn describe_window(&self, window: Window) -> Option<ForegroundEvent> {
+        if self.is_hidden(window) {
+            return None;
+        }
+
+        let pid = self
+            .conn
+            .get_property(
+                false,
+                window,
+                self.atoms._NET_WM_PID,
+                AtomEnum::CARDINAL,
+                0,
+                1,
+            )
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .and_then(|reply| reply.value32().and_then(|mut v| v.next()))
+            .unwrap_or(0);
+
+        let title = self
+            .property(window, self.atoms._NET_WM_NAME, self.atoms.UTF8_STRING)
+            .or_else(|| self.property(window, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()))
+            .map(|raw| String::from_utf8_lossy(&raw).into_owned())
+            .unwrap_or_default();
+
+        // WM_CLASS is "instance\0class\0"; the class half is the stable one.
+        let class = self
+            .property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())
+            .map(|raw| {
+                raw.split(|b| *b == 0)
+                    .filter(|part| !part.is_empty())
+                    .nth(1)
+                    .map(|part| String::from_utf8_lossy(part).into_owned())
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default();
+
+        Some(ForegroundEvent {
+            window: window as WindowId,
+            pid,
+            title,
+            class,
+        })
+    }
+}
+
+impl ForegroundSource for X11Source {
+    fn name(&self) -> &'static str {
+        "x11"
+    }
+
+    fn run(&self, mut handler: ForegroundHandler) -> Result<(), String> {
+        let attributes = ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE);
+        self.conn
+            .change_window_attributes(self.root, &attributes)
+            .map_err(|e| e.to_string())?
+            .check()
+            .map_err(|e| e.to_string())?;
+
+        let mut last = self.active_window();
+        let mut last_size = last.and_then(|w| self.window_size(w));
+        if let Some(window) = last {
+            self.watch_window(window, true);
+        }
+        if let Some(event) = last.and_then(|w| self.describe_window(w)) {
+            handler(event);
+        }
+
+        loop {
+            let event = self.conn.wait_for_event().map_err(|e| e.to_string())?;
+            let notify = match event {
+                Event::PropertyNotify(notify) => notify,
+                // The active window was resized, e.g. a game left its launcher
+                // window for fullscreen. Moves alone are not reported.
+                Event::ConfigureNotify(configure) if Some(configure.window) == last => {
+                    let size = Some((configure.width as i32, configure.height as i32));
+                    if size != last_size {
+                        if let Some(event) = self.describe_window(configure.window) {
+                            handler(event);
+                        }
+                    }
+                    last_size = size;
+                    continue;
+                }
+                _ => continue,
+            };
+
+            // The active window was renamed (an emulator loaded a game) or
+            // entered or left fullscreen.
+            let is_watched = notify.atom == self.atoms._NET_WM_NAME
+                || notify.atom == u32::from(AtomEnum::WM_NAME)
+                || notify.atom == self.atoms._NET_WM_STATE;
+            if Some(notify.window) == last && is_watched {
+                if let Some(event) = self.describe_window(notify.window) {
+                    handler(event);
+                }
+                continue;
+            }
+
+            if notify.window != self.root || notify.atom != self.atoms._NET_ACTIVE_WINDOW {
+                continue;
+            }
+
+            let active = self.active_window();
+            if active == last {
+                continue;
+            }
+            if let Some(window) = last {
+                self.watch_window(window, false);
+            }
+            if let Some(window) = active {
+                self.watch_window(window, true);
+            }
+            last = active;
+            last_size = active.and_then(|w| self.window_size(w));
+
+            if let Some(event) = active.and_then(|w| self.describe_window(w)) {
+                handler(event);
+            }
+        }
+    }
+
+    fn monitor_rect(&self, window: WindowId) -> Option<WindowRect> {
+        let rect = self.window_rect(window)?;
+        let (x, y) = ((rect.left + rect.right) / 2, (rect.top + rect.bottom) / 2);
+        let resources = self
+            .conn
+            .randr_get_screen_resources_current(self.root)
+            .ok()?
+            .reply()
+            .ok()?;
+        resources.crtcs.iter().find_map(|&crtc| {
+            let info = self
+                .conn
+                .randr_get_crtc_info(crtc, resources.config_timestamp)
+                .ok()?
+                .reply()
+                .ok()?;
+            let monitor = WindowRect {
+                left: info.x as i32,
+                top: info.y as i32,
+                right: info.x as i32 + info.width as i32,
+                bottom: info.y as i32 + info.height as i32,
+            };
+            let inside = (monitor.left..monitor.right).contains(&x)
+                && (monitor.top..monitor.bottom).contains(&y);
+            (info.mode != x11rb::NONE && inside).then_some(monitor)
+        })
+    }
+
+    /// Fullscreen windows, and windows asking the window manager for no
+    /// decorations through `_MOTIF_WM_HINTS`.
+    fn is_borderless(&self, window: WindowId) -> bool {
+        let window = window as Window;
+        if self.has_state(window, self.atoms._NET_WM_STATE_FULLSCREEN) {
+            return true;
+        }
+        // flags, functions, decorations, ...; bit 1 of flags says decorations is set
+        let hints: Vec<u32> = self
+            .property(
+                window,
+                self.atoms._MOTIF_WM_HINTS,
+                self.atoms._MOTIF_WM_HINTS,
+            )
+            .map(|raw| {
+                raw.chunks_exact(4)
+                    .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                    .collect()
+            })
+            .unwrap_or_default();
+        matches!(hints.as_slice(), [flags, _, decorations, ..] if flags & 2 != 0 && *decorations == 0)
+    }
+
+    fn confine_cursor(&self, rect: Option<WindowRect>) -> bool {
+        if !self.barriers_supported {
+            return false;
+        }
+        let mut confinement = self.confinement.lock().unwrap();
+        // Called many times a second while a window is locked; only a moved
+        // or resized window needs new barriers.
+        if confinement.as_ref().map(|(current, _)| *current) == rect {
+            return true;
+        }
+        if let Some((_, barriers)) = confinement.take() {
+            self.delete_barriers(barriers);
+        }
+        let Some(rect) = rect else {
+            return true;
+        };
+        match self.create_barriers(rect) {
+            Ok(barriers) => {
+                *confinement = Some((rect, barriers));
+                true
+            }
+            Err(e) => {
+                log::error!("Failed to create pointer barriers: {}", e);
+                false
+            }
+        }
+    }
+
+    fn window_rect(&self, window: WindowId) -> Option<WindowRect> {
+        let window = window as Window;
+        let geometry = self.conn.get_geometry(window).ok()?.reply().ok()?;
+        let origin = self
+            .conn
+            .translate_coordinates(window, self.root, 0, 0)
+            .ok()?
+            .reply()
+            .ok()?;
+
+        let left = origin.dst_x as i32;
+        let top = origin.dst_y as i32;
+        Some(WindowRect {
+            left,
+            top,
+            right: left + geometry.width as i32,
+            bottom: top + geometry.height as i32,
+        })
+    }
+}
@@ -0,0 +1,59 @@
+use windows::core::{w, PCWSTR};
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+    System::LibraryLoader::GetModuleHandleW,
+    UI::WindowsAndMessaging::{
+        CreateWindowExW, DispatchMessageW, GetMessageW, RegisterClassW, TranslateMessage, MSG,
+        WINDOW_EX_STYLE, WNDCLASSW, WS_OVERLAPPED,
+    },
+};
+
+pub type WindowProc = unsafe extern "system" fn(HWND, u32, WPARAM, LPARAM) -> LRESULT;
+
+/// Creates a hidden top-level window of class `class` handled by `window_proc`
+/// and pumps its messages until the loop ends, on the calling thread.
+///
+/// Broadcasts such as `WM_ENDSESSION`, `WM_DISPLAYCHANGE` and `WM_DEVICECHANGE`
+/// only reach top-level windows; a message-only window never sees them.
+/// `purpose` completes "Cannot watch for ..." in the log when setup fails.
+pub fn run(class: PCWSTR, window_proc: WindowProc, purpose: &str) {
+    unsafe {
+        let Ok(instance) = GetModuleHandleW(None) else {
+            log::warn!("Cannot watch for {}: no module handle", purpose);
+            return;
+        };
+        let wnd_class = WNDCLASSW {
+            lpfnWndProc: Some(window_proc),
+            hInstance: instance.into(),
+            lpszClassName: class,
+            ..Default::default()
+        };
+        if RegisterClassW(&wnd_class) == 0 {
+            log::warn!("Cannot watch for {}: RegisterClassW failed", purpose);
+            return;
+        }
+        if let Err(e) = CreateWindowExW(
+            WINDOW_EX_STYLE::default(),
+            class,
+            w!("Kortex"),
+            WS_OVERLAPPED,
+            0,
+            0,
+            0,
+            0,
+            None,
+            None,
+            Some(instance.into()),
+            None,
+        ) {
+            log::warn!("Cannot watch for {}: {}", purpose, e);
+            return;
+        }
+
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            let _ = DispatchMessageW(&msg);
+        }
+    }
+}
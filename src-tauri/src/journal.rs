@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager};
+
+use crate::resolution_manager::{self, DisplayError, Persistence, Resolution, Topology};
+
+/// The display state from before a profile was applied. It is written to disk
+/// while the profile is active, so a crash or a kill does not leave the user
+/// stuck in a game's resolution.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Journal {
+    /// Process whose profile changed the displays, for the log.
+    pub process: String,
+    /// Mode of every display a profile changed, tagged with its display.
+    #[serde(default)]
+    pub original_resolutions: Vec<Resolution>,
+    pub original_topology: Option<Topology>,
+    pub persistence: Persistence,
+}
+
+fn get_journal_path(app: &AppHandle) -> PathBuf {
+    app.path()
+        .app_config_dir()
+        .expect("failed to get app config dir")
+        .join("display-journal.json")
+}
+
+/// Writes the journal through a temporary file, so a crash halfway through
+/// never leaves a truncated journal behind.
+pub fn write(app: &AppHandle, journal: &Journal) -> Result<(), String> {
+    let path = get_journal_path(app);
+    if let Some(parent) = path.parent() {
+        let _ = fs::create_dir_all(parent);
+    }
+    let json = serde_json::to_string_pretty(journal).map_err(|e| e.to_string())?;
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, json).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, &path).map_err(|e| e.to_string())
+}
+
+/// Removes the journal once the displays are back to their original state.
+pub fn clear(app: &AppHandle) {
+    let path = get_journal_path(app);
+    if path.exists() {
+        if let Err(e) = fs::remove_file(&path) {
+            log::error!("Failed to remove display journal: {}", e);
+        }
+    }
+}
+
+fn read(app: &AppHandle) -> Option<Journal> {
+    let content = fs::read_to_string(get_journal_path(app)).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(journal) => Some(journal),
+        Err(e) => {
+            log::error!("Ignoring unreadable display journal: {}", e);
+            None
+        }
+    }
+}
+
+fn restore(journal: &Journal) -> Result<(), DisplayError> {
+    if let Some(topology) = &journal.original_topology {
+        resolution_manager::apply_topology(topology, journal.persistence)?;
+    }
+    // Every display is tried, so one missing monitor does not keep the
+    // others in the profile's mode; the first error is reported.
+    let mut result = Ok(());
+    for res in &journal.original_resolutions {
+        if let Err(e) = resolution_manager::change_resolution(res.clone(), journal.persistence) {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+    result
+}
+
+/// Called on startup, before the monitor hook. A journal left on disk means the
+/// last session ended while a profile was active, so its display state is put
+/// back. The journal is kept when the display is not connected right now, so
+/// the next start can try again.
+pub fn restore_unfinished(app: &AppHandle) {
+    let Some(journal) = read(app) else {
+        clear(app);
+        return;
+    };
+
+    log::warn!(
+        "Previous session ended while the profile for {} was active, restoring displays",
+        journal.process
+    );
+    match restore(&journal) {
+        Ok(()) => {
+            log::info!("Restored display state from journal.");
+            clear(app);
+        }
+        Err(e @ DisplayError::DeviceNotFound { .. }) => {
+            log::warn!("Could not restore display state yet: {}", e);
+        }
+        Err(e) => {
+            log::error!("Failed to restore display state from journal: {}", e);
+            clear(app);
+        }
+    }
+}
@@ -1,15 +1,32 @@
 mod config_manager;
+mod confirm_manager;
+mod display_events;
+mod foreground;
+#[cfg(target_os = "windows")]
+mod hidden_window;
+mod journal;
+mod matcher;
+mod process_cache;
 mod process_monitor;
+mod process_watcher;
 mod resolution_manager;
+mod session;
 
 use config_manager::{get_config, save_config};
 use process_monitor::{start_monitor_hook, ProcessInfo};
-use resolution_manager::Resolution;
+use resolution_manager::{
+    DisplayError, DisplayInfo, DisplayTarget, Persistence, Resolution, Topology,
+};
 use tauri::Manager;
 
 #[tauri::command]
-fn get_resolutions() -> Vec<Resolution> {
-    resolution_manager::get_supported_resolutions()
+fn get_displays() -> Vec<DisplayInfo> {
+    resolution_manager::get_displays()
+}
+
+#[tauri::command]
+fn get_resolutions(display: Option<String>) -> Vec<Resolution> {
+    resolution_manager::get_supported_resolutions(display.map(DisplayTarget::device).as_ref())
 }
 
 #[tauri::command]
@@ -18,24 +35,94 @@ fn get_app_version(app: tauri::AppHandle) -> String {
 }
 
 #[tauri::command]
-fn get_current_res() -> Option<Resolution> {
-    resolution_manager::get_current_resolution()
+fn get_current_res(display: Option<String>) -> Option<Resolution> {
+    resolution_manager::get_current_resolution(display.map(DisplayTarget::device).as_ref())
+}
+
+/// `mode` carries the mode and its optional format and rotation; `display` is
+/// the device name picked in the UI. Changes are temporary unless
+/// `persistence` says otherwise, and are rolled back after `timeout` seconds
+/// unless `confirm_resolution` arrives first.
+#[tauri::command]
+fn set_resolution(
+    app: tauri::AppHandle,
+    mode: Resolution,
+    display: Option<String>,
+    persistence: Option<Persistence>,
+    timeout: Option<u64>,
+) -> Result<(), DisplayError> {
+    confirm_manager::apply_with_confirmation(
+        &app,
+        Resolution {
+            display: display.map(DisplayTarget::device),
+            ..mode
+        },
+        persistence.unwrap_or_default(),
+        timeout.unwrap_or(confirm_manager::DEFAULT_TIMEOUT_SECS),
+    )
+}
+
+#[tauri::command]
+fn confirm_resolution(app: tauri::AppHandle) -> bool {
+    confirm_manager::confirm(&app)
 }
 
 #[tauri::command]
-fn set_resolution(width: u32, height: u32, frequency: u32) -> Result<(), String> {
-    resolution_manager::change_resolution(Resolution {
-        width,
-        height,
-        frequency,
+fn revert_resolution(app: tauri::AppHandle) -> bool {
+    confirm_manager::revert(&app)
+}
+
+#[tauri::command]
+fn test_resolution(mode: Resolution, display: Option<String>) -> Result<(), DisplayError> {
+    resolution_manager::test_resolution(&Resolution {
+        display: display.map(DisplayTarget::device),
+        ..mode
     })
 }
 
+#[tauri::command]
+fn get_topology() -> Result<Topology, DisplayError> {
+    resolution_manager::get_topology()
+}
+
+/// Applies a layout; like `set_resolution`, temporary unless `persistence`
+/// says otherwise.
+#[tauri::command]
+fn set_topology(topology: Topology, persistence: Option<Persistence>) -> Result<(), DisplayError> {
+    resolution_manager::apply_topology(&topology, persistence.unwrap_or_default())
+}
+
 #[tauri::command]
 fn fetch_processes() -> Vec<ProcessInfo> {
     process_monitor::get_running_processes()
 }
 
+/// Lists the running processes a rule would match, so it can be checked
+/// before saving.
+#[tauri::command]
+fn test_matcher(matcher: matcher::ProcessMatcher) -> Result<Vec<ProcessInfo>, String> {
+    matcher.validate()?;
+    Ok(process_monitor::matching_processes(&matcher))
+}
+
+/// Shows which profile would win for the running process `pid` and why;
+/// `title` and `class` stand in for its window.
+#[tauri::command]
+fn explain_match(
+    app: tauri::AppHandle,
+    pid: u32,
+    title: Option<String>,
+    class: Option<String>,
+) -> Result<process_monitor::MatchExplanation, String> {
+    let config = get_config(app);
+    process_monitor::explain_match(
+        &config.automation.auto_res.profiles,
+        pid,
+        title.unwrap_or_default(),
+        class.unwrap_or_default(),
+    )
+}
+
 #[tauri::command]
 fn force_revert() {
     process_monitor::force_revert();
@@ -50,9 +137,18 @@ fn open_config_folder(app: tauri::AppHandle) {
         use std::process::Command;
         #[cfg(target_os = "windows")]
         let _ = Command::new("explorer").arg(path).spawn();
+        #[cfg(target_os = "linux")]
+        let _ = Command::new("xdg-open").arg(path).spawn();
     }
 }
 
+/// Puts the displays back before the process goes away: a pending manual
+/// change is rolled back, then the active profile is reverted.
+fn shutdown(app: &tauri::AppHandle) {
+    confirm_manager::shutdown(app);
+    process_monitor::shutdown();
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     use tauri::menu::{Menu, MenuItem};
@@ -122,20 +218,44 @@ pub fn run() {
                 })
                 .build(app)?;
 
+            // Undo a profile left active by a crash before anything else touches
+            // the displays.
+            journal::restore_unfinished(app.handle());
             start_monitor_hook(app.handle().clone());
+
+            // Logoff and SIGTERM do not go through the tray's exit path. The
+            // displays are restored first, as the OS may not wait for the exit.
+            let session_app = app.handle().clone();
+            session::watch(Box::new(move || {
+                shutdown(&session_app);
+                session_app.exit(0);
+            }));
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
+            get_displays,
             get_resolutions,
             get_current_res,
             set_resolution,
+            confirm_resolution,
+            revert_resolution,
+            test_resolution,
+            get_topology,
+            set_topology,
             fetch_processes,
+            test_matcher,
+            explain_match,
             open_config_folder,
             get_config,
             save_config,
             get_app_version,
             force_revert
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::Exit = event {
+                shutdown(app);
+            }
+        });
 }
@@ -0,0 +1,567 @@
+use glob::{MatchOptions, Pattern};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use crate::foreground::WindowRect;
+
+// Executable paths are case-insensitive on Windows only.
+const CASE_SENSITIVE_PATHS: bool = !cfg!(target_os = "windows");
+
+/// How a profile or mouse-lock rule picks out a process. Serialized as
+/// `{ "kind": "glob", "pattern": "C:/Games/**/*.exe" }`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ProcessMatcher {
+    /// Executable name, with or without its extension, ignoring case.
+    Name { name: String },
+    /// Full path of the executable.
+    Path { path: String },
+    /// Any executable inside this folder or below it, e.g. a Steam library.
+    PathPrefix { path: String },
+    /// Glob over the full path, or over the name when the pattern has no
+    /// path separator.
+    Glob { pattern: String },
+    /// Regular expression searched for in the full path (or the name when the
+    /// path is unknown).
+    Regex { pattern: String },
+}
+
+/// A test on a piece of text such as a window title.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum TextMatcher {
+    /// Substring, ignoring case.
+    Contains { text: String },
+    /// Regular expression searched for in the text; case-sensitive unless it
+    /// starts with `(?i)`.
+    Regex { pattern: String },
+}
+
+impl TextMatcher {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            TextMatcher::Contains { .. } => Ok(()),
+            TextMatcher::Regex { pattern } => compiled_regex(pattern, false).map(|_| ()),
+        }
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            TextMatcher::Contains { text: needle } => {
+                text.to_lowercase().contains(&needle.to_lowercase())
+            }
+            TextMatcher::Regex { pattern } => {
+                compiled_regex(pattern, false).is_ok_and(|re| re.is_match(text))
+            }
+        }
+    }
+}
+
+/// Extra conditions a rule can put on top of its process matcher, e.g. one
+/// profile per game running in the same emulator. All set ones must hold.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchConditions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_title: Option<TextMatcher>,
+    /// Window class (the `WM_CLASS` class on X11).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window_class: Option<TextMatcher>,
+    /// Arguments after the executable, joined with spaces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command_line: Option<TextMatcher>,
+    /// Some process up the parent chain, e.g. `steam.exe` for games started
+    /// from Steam.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ancestor: Option<ProcessMatcher>,
+    /// The window covers its whole monitor (exclusive or borderless fullscreen).
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub fullscreen: bool,
+    /// The window has no title bar or frame.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub borderless: bool,
+    /// Smallest window size that counts, so launchers and splash screens of
+    /// the same executable are left alone.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_height: Option<u32>,
+}
+
+/// Geometry of the foreground window when rules are evaluated.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowState {
+    pub rect: WindowRect,
+    /// Bounds of the monitor the window is on, if known.
+    pub monitor: Option<WindowRect>,
+    pub borderless: bool,
+}
+
+impl WindowState {
+    pub fn covers_monitor(&self) -> bool {
+        self.monitor.is_some_and(|m| {
+            self.rect.left <= m.left
+                && self.rect.top <= m.top
+                && self.rect.right >= m.right
+                && self.rect.bottom >= m.bottom
+        })
+    }
+
+    fn width(&self) -> u32 {
+        (self.rect.right - self.rect.left).max(0) as u32
+    }
+
+    fn height(&self) -> u32 {
+        (self.rect.bottom - self.rect.top).max(0) as u32
+    }
+}
+
+impl MatchConditions {
+    pub fn validate(&self) -> Result<(), String> {
+        [&self.window_title, &self.window_class, &self.command_line]
+            .into_iter()
+            .flatten()
+            .try_for_each(TextMatcher::validate)?;
+        self.ancestor
+            .as_ref()
+            .map_or(Ok(()), ProcessMatcher::validate)
+    }
+
+    /// Number of conditions set; more of them make a rule more specific.
+    pub fn count(&self) -> usize {
+        [&self.window_title, &self.window_class, &self.command_line]
+            .into_iter()
+            .filter(|c| c.is_some())
+            .count()
+            + [
+                self.ancestor.is_some(),
+                self.fullscreen,
+                self.borderless,
+                self.min_width.is_some() || self.min_height.is_some(),
+            ]
+            .into_iter()
+            .filter(|set| *set)
+            .count()
+    }
+
+    /// Whether any condition needs the foreground window's geometry.
+    pub fn needs_window(&self) -> bool {
+        self.fullscreen || self.borderless || self.min_width.is_some() || self.min_height.is_some()
+    }
+
+    /// These conditions without the ones on window geometry, for checking a
+    /// process that has no window to measure.
+    pub fn without_window(&self) -> MatchConditions {
+        MatchConditions {
+            fullscreen: false,
+            borderless: false,
+            min_width: None,
+            min_height: None,
+            ..self.clone()
+        }
+    }
+
+    /// Whether every condition set here is also set, identically, in `other`,
+    /// so anything `other` lets through passes these conditions too.
+    pub fn covers(&self, other: &MatchConditions) -> bool {
+        fn field<T: PartialEq>(mine: &Option<T>, theirs: &Option<T>) -> bool {
+            mine.is_none() || mine == theirs
+        }
+        fn at_most(mine: Option<u32>, theirs: Option<u32>) -> bool {
+            mine.map_or(true, |mine| theirs.is_some_and(|theirs| mine <= theirs))
+        }
+        field(&self.window_title, &other.window_title)
+            && field(&self.window_class, &other.window_class)
+            && field(&self.command_line, &other.command_line)
+            && field(&self.ancestor, &other.ancestor)
+            && (!self.fullscreen || other.fullscreen)
+            && (!self.borderless || other.borderless)
+            && at_most(self.min_width, other.min_width)
+            && at_most(self.min_height, other.min_height)
+    }
+
+    pub fn matches(&self, subject: &MatchSubject) -> bool {
+        let text = |matcher: &Option<TextMatcher>, text: &str| {
+            matcher.as_ref().map_or(true, |m| m.matches(text))
+        };
+        text(&self.window_title, &subject.title)
+            && text(&self.window_class, &subject.class)
+            && text(&self.command_line, &subject.args.join(" "))
+            && self
+                .ancestor
+                .as_ref()
+                .map_or(true, |m| subject.ancestors.iter().any(|a| m.matches(a)))
+            && (!self.needs_window() || subject.window.is_some_and(|w| self.window_matches(&w)))
+    }
+
+    fn window_matches(&self, window: &WindowState) -> bool {
+        (!self.fullscreen || window.covers_monitor())
+            && (!self.borderless || window.borderless)
+            && self.min_width.map_or(true, |w| window.width() >= w)
+            && self.min_height.map_or(true, |h| window.height() >= h)
+    }
+}
+
+/// What is known about a process and its foreground window when rules are
+/// evaluated.
+#[derive(Debug, Clone, Default)]
+pub struct MatchSubject {
+    pub name: String,
+    /// Full executable path; unknown for some protected processes.
+    pub path: Option<PathBuf>,
+    /// Title and class of the foreground window; empty when listing processes.
+    pub title: String,
+    pub class: String,
+    /// Command-line arguments, without the executable.
+    pub args: Vec<String>,
+    /// Parent first, then its parent and so on; only name and path are set.
+    pub ancestors: Vec<MatchSubject>,
+    /// Foreground window geometry; `None` when listing processes.
+    pub window: Option<WindowState>,
+}
+
+impl MatchSubject {
+    fn path_or_name(&self) -> String {
+        match &self.path {
+            Some(path) => path.to_string_lossy().into_owned(),
+            None => self.name.clone(),
+        }
+    }
+}
+
+/// Orders rules that match the same process: explicit priority first, then
+/// how specific the matcher is, then how many extra conditions the rule has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleRank {
+    pub priority: i32,
+    pub specificity: u8,
+    pub conditions: usize,
+}
+
+impl ProcessMatcher {
+    /// The matcher of a rule that only has a process name, as in configs
+    /// written before matchers existed.
+    pub fn for_rule(process: &str, matcher: Option<&ProcessMatcher>) -> ProcessMatcher {
+        matcher.cloned().unwrap_or_else(|| ProcessMatcher::Name {
+            name: process.to_string(),
+        })
+    }
+
+    /// How narrowly the matcher picks out a process: a full path beats a
+    /// folder, which beats a name, which beats a glob or regex.
+    pub fn specificity(&self) -> u8 {
+        match self {
+            ProcessMatcher::Path { .. } => 3,
+            ProcessMatcher::PathPrefix { .. } => 2,
+            ProcessMatcher::Name { .. } => 1,
+            ProcessMatcher::Glob { .. } | ProcessMatcher::Regex { .. } => 0,
+        }
+    }
+
+    /// Short name of the kind of matcher, for explanations.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ProcessMatcher::Name { .. } => "name",
+            ProcessMatcher::Path { .. } => "path",
+            ProcessMatcher::PathPrefix { .. } => "folder",
+            ProcessMatcher::Glob { .. } => "glob",
+            ProcessMatcher::Regex { .. } => "regex",
+        }
+    }
+
+    /// Whether every process `other` matches is matched by this one too, as
+    /// far as can be told from the rules alone. Patterns are only compared
+    /// against full paths, so a glob covering another glob is not detected.
+    pub fn covers(&self, other: &ProcessMatcher) -> bool {
+        if self == other {
+            return true;
+        }
+        match (self, other) {
+            (_, ProcessMatcher::Path { path }) => {
+                let path = Path::new(path);
+                self.matches(&MatchSubject {
+                    name: path
+                        .file_name()
+                        .map(|n| n.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    path: Some(path.to_path_buf()),
+                    ..Default::default()
+                })
+            }
+            (ProcessMatcher::PathPrefix { path }, ProcessMatcher::PathPrefix { path: inner }) => {
+                normalize(Path::new(inner)).starts_with(normalize(Path::new(path)))
+            }
+            (ProcessMatcher::Name { name }, ProcessMatcher::Name { name: inner }) => {
+                name_matches(name, inner)
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks that a glob or regex pattern compiles.
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            ProcessMatcher::Glob { pattern } => Pattern::new(pattern)
+                .map(|_| ())
+                .map_err(|e| format!("Invalid glob {:?}: {}", pattern, e)),
+            ProcessMatcher::Regex { pattern } => {
+                compiled_regex(pattern, !CASE_SENSITIVE_PATHS).map(|_| ())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn matches(&self, subject: &MatchSubject) -> bool {
+        match self {
+            ProcessMatcher::Name { name } => name_matches(name, &subject.name),
+            ProcessMatcher::Path { path } => subject
+                .path
+                .as_ref()
+                .is_some_and(|p| normalize(p) == normalize(Path::new(path))),
+            ProcessMatcher::PathPrefix { path } => subject
+                .path
+                .as_ref()
+                .is_some_and(|p| normalize(p).starts_with(normalize(Path::new(path)))),
+            ProcessMatcher::Glob { pattern } => {
+                let Ok(pattern) = Pattern::new(pattern) else {
+                    return false;
+                };
+                let options = MatchOptions {
+                    case_sensitive: CASE_SENSITIVE_PATHS,
+                    require_literal_separator: false,
+                    require_literal_leading_dot: false,
+                };
+                if pattern.as_str().contains(['/', '\\']) {
+                    subject
+                        .path
+                        .as_ref()
+                        .is_some_and(|p| pattern.matches_path_with(p, options))
+                } else {
+                    pattern.matches_with(&subject.name, options)
+                }
+            }
+            ProcessMatcher::Regex { pattern } => compiled_regex(pattern, !CASE_SENSITIVE_PATHS)
+                .is_ok_and(|re| re.is_match(&subject.path_or_name())),
+        }
+    }
+}
+
+/// The legacy rule: exact name or file stem, ignoring case.
+fn name_matches(rule: &str, name: &str) -> bool {
+    let rule = rule.to_lowercase();
+    let name = name.to_lowercase();
+    let stem = Path::new(&name)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or(&name);
+    name == rule || stem == rule
+}
+
+/// Path for comparisons: forward and back slashes unified, and lowercased
+/// where the file system ignores case.
+fn normalize(path: &Path) -> PathBuf {
+    let path = path.to_string_lossy();
+    if CASE_SENSITIVE_PATHS {
+        PathBuf::from(path.as_ref())
+    } else {
+        PathBuf::from(path.replace('/', "\\").to_lowercase())
+    }
+}
+
+// Rules are evaluated on every focus change; compile each pattern once.
+static REGEX_CACHE: OnceLock<Mutex<HashMap<(String, bool), Regex>>> = OnceLock::new();
+
+fn compiled_regex(pattern: &str, case_insensitive: bool) -> Result<Regex, String> {
+    let cache = REGEX_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    let key = (pattern.to_string(), case_insensitive);
+    if let Some(re) = cache.lock().ok().and_then(|c| c.get(&key).cloned()) {
+        return Ok(re);
+    }
+    let re = RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .build()
+        .map_err(|e| format!("Invalid regex {:?}: {}", pattern, e))?;
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(key, re.clone());
+    }
+    Ok(re)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(path: &str) -> MatchSubject {
+        let path = Path::new(path);
+        MatchSubject {
+            name: path.file_name().unwrap().to_string_lossy().into_owned(),
+            path: Some(path.to_path_buf()),
+            ..Default::default()
+        }
+    }
+
+    fn rect(left: i32, top: i32, right: i32, bottom: i32) -> WindowRect {
+        WindowRect {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    fn with_window(window: WindowRect, borderless: bool) -> MatchSubject {
+        MatchSubject {
+            window: Some(WindowState {
+                rect: window,
+                monitor: Some(rect(0, 0, 1920, 1080)),
+                borderless,
+            }),
+            ..process("/games/game.exe")
+        }
+    }
+
+    fn name(name: &str) -> ProcessMatcher {
+        ProcessMatcher::Name {
+            name: name.to_string(),
+        }
+    }
+
+    fn path(path: &str) -> ProcessMatcher {
+        ProcessMatcher::Path {
+            path: path.to_string(),
+        }
+    }
+
+    fn folder(path: &str) -> ProcessMatcher {
+        ProcessMatcher::PathPrefix {
+            path: path.to_string(),
+        }
+    }
+
+    fn glob(pattern: &str) -> ProcessMatcher {
+        ProcessMatcher::Glob {
+            pattern: pattern.to_string(),
+        }
+    }
+
+    #[test]
+    fn name_matches_with_or_without_extension() {
+        let game = process("/games/Game.exe");
+        assert!(name("game").matches(&game));
+        assert!(name("GAME.EXE").matches(&game));
+        assert!(!name("gam").matches(&game));
+    }
+
+    #[test]
+    fn path_and_folder_match_whole_components() {
+        let game = process("/games/steam/common/game.exe");
+        assert!(path("/games/steam/common/game.exe").matches(&game));
+        assert!(!path("/games/steam/game.exe").matches(&game));
+        assert!(folder("/games/steam").matches(&game));
+        assert!(!folder("/games/ste").matches(&game));
+        assert!(!folder("/games").matches(&MatchSubject { path: None, ..game }));
+    }
+
+    #[test]
+    fn glob_matches_the_path_only_with_a_separator() {
+        let game = process("/games/emu/retroarch.exe");
+        assert!(glob("retro*").matches(&game));
+        assert!(glob("/games/**/*.exe").matches(&game));
+        assert!(!glob("/games/**/retro*").matches(&process("/other/retroarch.exe")));
+    }
+
+    #[test]
+    fn regex_falls_back_to_the_name_without_a_path() {
+        let regex = ProcessMatcher::Regex {
+            pattern: r"emu/.*\.exe$".to_string(),
+        };
+        assert!(regex.matches(&process("/games/emu/retroarch.exe")));
+        assert!(!regex.matches(&MatchSubject {
+            path: None,
+            ..process("/games/emu/retroarch.exe")
+        }));
+    }
+
+    #[test]
+    fn specificity_ranks_path_over_folder_over_name_over_patterns() {
+        let order = [
+            path("/games/game.exe").specificity(),
+            folder("/games").specificity(),
+            name("game").specificity(),
+            glob("game*").specificity(),
+        ];
+        assert!(order.windows(2).all(|pair| pair[0] > pair[1]));
+    }
+
+    #[test]
+    fn covers_paths_inside_a_folder_or_with_the_same_name() {
+        assert!(folder("/games").covers(&path("/games/steam/game.exe")));
+        assert!(folder("/games").covers(&folder("/games/steam")));
+        assert!(!folder("/games/steam").covers(&folder("/games")));
+        assert!(name("game").covers(&path("/games/game.exe")));
+        assert!(name("game").covers(&name("game.exe")));
+        assert!(!path("/games/game.exe").covers(&name("game")));
+        // Patterns are only compared against full paths.
+        assert!(!glob("game*").covers(&name("game")));
+    }
+
+    #[test]
+    fn fullscreen_needs_a_window_covering_its_monitor() {
+        let conditions = MatchConditions {
+            fullscreen: true,
+            ..Default::default()
+        };
+        assert!(conditions.matches(&with_window(rect(0, 0, 1920, 1080), false)));
+        assert!(!conditions.matches(&with_window(rect(0, 0, 1280, 720), false)));
+        // Processes are listed without a window.
+        assert!(!conditions.matches(&process("/games/game.exe")));
+    }
+
+    #[test]
+    fn borderless_and_minimum_size_check_the_window() {
+        let conditions = MatchConditions {
+            borderless: true,
+            min_width: Some(1280),
+            min_height: Some(720),
+            ..Default::default()
+        };
+        assert!(conditions.matches(&with_window(rect(100, 100, 1380, 820), true)));
+        assert!(!conditions.matches(&with_window(rect(100, 100, 1380, 820), false)));
+        assert!(!conditions.matches(&with_window(rect(100, 100, 900, 600), true)));
+    }
+
+    #[test]
+    fn conditions_cover_only_stricter_ones() {
+        let none = MatchConditions::default();
+        let small = MatchConditions {
+            min_width: Some(800),
+            ..Default::default()
+        };
+        let large = MatchConditions {
+            min_width: Some(1280),
+            fullscreen: true,
+            ..Default::default()
+        };
+        assert!(none.covers(&large));
+        assert!(small.covers(&large));
+        assert!(!large.covers(&small));
+        assert!(!small.covers(&none));
+        assert_eq!(large.count(), 2);
+    }
+
+    #[test]
+    fn rule_rank_orders_priority_then_specificity_then_conditions() {
+        let rank = |priority, specificity, conditions| RuleRank {
+            priority,
+            specificity,
+            conditions,
+        };
+        assert!(rank(1, 0, 0) > rank(0, 3, 5));
+        assert!(rank(0, 3, 0) > rank(0, 1, 5));
+        assert!(rank(0, 1, 2) > rank(0, 1, 1));
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// What rules need to know about a process. None of it changes while the
+/// process runs, so it is looked up once per PID.
+#[derive(Debug)]
+pub struct CachedProcess {
+    pub name: String,
+    pub path: Option<PathBuf>,
+    /// Command-line arguments, without the executable.
+    pub args: Vec<String>,
+    pub parent: Option<u32>,
+}
+
+#[derive(Default)]
+struct ProcessCache {
+    entries: HashMap<u32, Arc<CachedProcess>>,
+    // Kept around because setting up a `System` costs more than reading one
+    // process through it
+    #[cfg(not(target_os = "linux"))]
+    sys: sysinfo::System,
+}
+
+static CACHE: OnceLock<Mutex<ProcessCache>> = OnceLock::new();
+
+fn cache() -> &'static Mutex<ProcessCache> {
+    CACHE.get_or_init(|| Mutex::new(ProcessCache::default()))
+}
+
+/// Returns the process `pid`, reading only that process from the system the
+/// first time it is asked for.
+pub fn lookup(pid: u32) -> Option<Arc<CachedProcess>> {
+    let mut cache = cache().lock().unwrap();
+    if let Some(entry) = cache.entries.get(&pid) {
+        return Some(entry.clone());
+    }
+
+    let entry = Arc::new(resolve(&mut cache, pid)?);
+    cache.entries.insert(pid, entry.clone());
+    Some(entry)
+}
+
+/// Drops what is known about `pid`. Called when the process exits, and when a
+/// new process shows up under a PID that might have been reused.
+pub fn invalidate(pid: u32) {
+    cache().lock().unwrap().entries.remove(&pid);
+}
+
+// sysinfo walks all of /proc even when asked for one PID, so the few files
+// rules need are read directly.
+#[cfg(target_os = "linux")]
+fn resolve(_cache: &mut ProcessCache, pid: u32) -> Option<CachedProcess> {
+    use std::fs;
+
+    let dir = PathBuf::from(format!("/proc/{}", pid));
+    // "pid (comm) state ppid ...", where comm may itself contain ") "
+    let stat = fs::read_to_string(dir.join("stat")).ok()?;
+    let (head, tail) = stat.rsplit_once(')')?;
+    let (_, name) = head.split_once('(')?;
+    let parent = tail
+        .split_whitespace()
+        .nth(1)
+        .and_then(|ppid| ppid.parse().ok())
+        .filter(|&ppid| ppid != 0);
+
+    let cmdline = fs::read(dir.join("cmdline")).unwrap_or_default();
+    // The first entry is the executable itself.
+    let args = cmdline
+        .strip_suffix(&[0])
+        .unwrap_or(&cmdline)
+        .split(|&b| b == 0)
+        .skip(1)
+        .map(|arg| String::from_utf8_lossy(arg).into_owned())
+        .collect();
+
+    Some(CachedProcess {
+        name: name.to_string(),
+        path: fs::read_link(dir.join("exe")).ok(),
+        args,
+        parent,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resolve(cache: &mut ProcessCache, pid: u32) -> Option<CachedProcess> {
+    use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, UpdateKind};
+
+    let pid = Pid::from_u32(pid);
+    // Also drops the PID from `sys` if the process is gone
+    cache.sys.refresh_processes_specifics(
+        ProcessesToUpdate::Some(&[pid]),
+        true,
+        ProcessRefreshKind::nothing()
+            .with_exe(UpdateKind::Always)
+            .with_cmd(UpdateKind::Always),
+    );
+    let process = cache.sys.process(pid)?;
+    Some(CachedProcess {
+        name: process.name().to_string_lossy().into_owned(),
+        path: process.exe().map(PathBuf::from),
+        // The first entry is the executable itself.
+        args: process
+            .cmd()
+            .iter()
+            .skip(1)
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+        parent: process.parent().map(Pid::as_u32),
+    })
+}
@@ -1,122 +1,410 @@
-use crate::resolution_manager::{change_resolution, get_current_resolution, Resolution};
+use crate::config_manager::{best_profile, ExternalChangePolicy, ProfileConfig, Trigger};
+use crate::display_events::{self, DisplayEvent};
+use crate::foreground::{self, ForegroundEvent, WindowId, WindowRect};
+use crate::journal;
+use crate::matcher::{MatchSubject, ProcessMatcher, RuleRank, WindowState};
+use crate::process_cache;
+use crate::process_watcher::{self, ProcessEvent};
+use crate::resolution_manager::{
+    apply_topology, change_resolution, get_current_resolution, get_displays, get_topology,
+    resolve_display, resolve_mode, DisplayError, DisplayInfo, Persistence, Resolution, Topology,
+};
 use serde::{Deserialize, Serialize};
-use std::sync::{Arc, Mutex};
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
 use std::time::{Duration, Instant};
-use sysinfo::{Pid, ProcessesToUpdate, System};
+use sysinfo::{Process, ProcessesToUpdate, System};
 use tauri::AppHandle;
 use tauri::Emitter;
-use windows::Win32::{
-    Foundation::{HWND, RECT},
-    UI::{
-        Accessibility::{SetWinEventHook, HWINEVENTHOOK},
-        // Input::KeyboardAndMouse::ClipCursor,
-        WindowsAndMessaging::{
-            ClipCursor, DispatchMessageW, GetMessageW, GetWindowRect, GetWindowThreadProcessId,
-            IsIconic, TranslateMessage, EVENT_SYSTEM_FOREGROUND, MSG, WINEVENT_OUTOFCONTEXT,
-        },
-    },
-};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProcessInfo {
     pub pid: u32,
     pub name: String,
+    pub path: Option<String>,
     pub memory: u64,
 }
 
 // Global state for key monitoring data
 struct MonitorState {
-    original_resolution: Option<Resolution>,
-    active_process: Option<String>, // Name of the process forcing the resolution
+    original_resolutions: HashMap<String, Resolution>, // Mode before any profile, per device name
+    original_topology: Option<Topology>,               // Captured before the first topology profile
+    active_profile: Option<ProfileConfig>,             // Profile forcing the resolution
+    active_pid: Option<u32>, // Process it was applied for; its exit reverts
+    active_mode: Option<Resolution>, // Mode actually applied for it (after fallback)
+    persistence: Persistence, // How it was applied; the revert matches it
     revert_pending: Option<Instant>, // Time when revert was requested
-    locked_window: Option<usize>,
+    locked_window: Option<WindowId>,
     locked_window_padding: (u32, u32),
     locked_process: Option<String>,
+    foreground_profile: Option<ProfileConfig>, // Profile matching the foreground process
+    displays: Vec<DisplayInfo>,                // Connected displays, to spot hot-plugs
 }
 
 // Global AppHandle for the hook callback
-static mut APP_HANDLE: Option<AppHandle> = None;
+static APP_HANDLE: OnceLock<AppHandle> = OnceLock::new();
 static STATE: Mutex<Option<Arc<Mutex<MonitorState>>>> = Mutex::new(None);
+// Set once the app is exiting; focus changes and timers are ignored from then on
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+// Set once a failed mouse lock was reported, so it is not logged on every focus change
+static MOUSE_LOCK_WARNED: AtomicBool = AtomicBool::new(false);
+
+// How far up the parent chain rules can look; also stops on a PID cycle
+const MAX_ANCESTORS: usize = 16;
+
+// Pause before retrying a display change that failed for a transient reason
+const RETRY_DELAY: Duration = Duration::from_millis(500);
 
 pub fn get_running_processes() -> Vec<ProcessInfo> {
+    list_processes(|_, _| true)
+}
+
+/// Running processes `matcher` picks out, for trying a rule before saving it.
+pub fn matching_processes(matcher: &ProcessMatcher) -> Vec<ProcessInfo> {
+    list_processes(|sys, process| matcher.matches(&match_subject(sys, process)))
+}
+
+fn list_processes(filter: impl Fn(&System, &Process) -> bool) -> Vec<ProcessInfo> {
     let mut sys = System::new_all();
     sys.refresh_processes(ProcessesToUpdate::All, true);
 
     let mut procs: Vec<ProcessInfo> = sys
         .processes()
         .iter()
+        .filter(|(_, process)| filter(&sys, process))
         .map(|(pid, process)| ProcessInfo {
             pid: pid.as_u32(),
             name: process.name().to_string_lossy().into_owned(),
+            path: process.exe().map(|p| p.to_string_lossy().into_owned()),
             memory: process.memory(),
         })
         .collect();
 
     // Sort by Memory Usage (Descending)
-    procs.sort_by(|a, b| b.memory.cmp(&a.memory));
+    procs.sort_by_key(|p| std::cmp::Reverse(p.memory));
 
     procs
 }
 
-// Hook Callback
-unsafe extern "system" fn win_event_hook(
-    _h_win_event_hook: HWINEVENTHOOK,
-    event: u32,
-    hwnd: HWND,
-    _id_object: i32,
-    _id_child: i32,
-    _id_event_thread: u32,
-    _dw_ms_event_time: u32,
-) {
-    if event == EVENT_SYSTEM_FOREGROUND {
-        check_and_apply_window(hwnd, "Hook");
+fn window_state(window: WindowId) -> Option<WindowState> {
+    let source = foreground::source()?;
+    Some(WindowState {
+        rect: source.window_rect(window)?,
+        monitor: source.monitor_rect(window),
+        borderless: source.is_borderless(window),
+    })
+}
+
+/// Subject for the running process `pid`, resolved through the process cache
+/// so a focus change only reads the processes involved.
+fn cached_subject(pid: u32) -> Option<MatchSubject> {
+    let process = process_cache::lookup(pid)?;
+
+    let mut ancestors = Vec::new();
+    let mut parent = process.parent.and_then(process_cache::lookup);
+    while let Some(ancestor) = parent.filter(|_| ancestors.len() < MAX_ANCESTORS) {
+        ancestors.push(MatchSubject {
+            name: ancestor.name.clone(),
+            path: ancestor.path.clone(),
+            ..Default::default()
+        });
+        parent = ancestor.parent.and_then(process_cache::lookup);
+    }
+
+    Some(MatchSubject {
+        name: process.name.clone(),
+        path: process.path.clone(),
+        args: process.args.clone(),
+        ancestors,
+        ..Default::default()
+    })
+}
+
+/// How one profile fared in `explain_match`.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuleVerdict {
+    pub index: usize,
+    pub process: String,
+    /// Kind of matcher, e.g. "path" or "glob".
+    pub matcher: &'static str,
+    pub matched: bool,
+    /// The profile has window-state conditions, which were not checked:
+    /// `matched` says whether everything else holds.
+    pub window_unchecked: bool,
+    pub rank: RuleRank,
+}
+
+/// Which profile wins for a process, and why.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MatchExplanation {
+    pub name: String,
+    pub path: Option<String>,
+    pub rules: Vec<RuleVerdict>,
+    /// Index of the winning profile.
+    pub winner: Option<usize>,
+    pub reason: String,
+}
+
+/// Evaluates every profile against the running process `pid`, as if its
+/// window with `title` and `class` had focus. There is no window geometry to
+/// go by, so fullscreen, borderless and size conditions are assumed to hold.
+pub fn explain_match(
+    profiles: &[ProfileConfig],
+    pid: u32,
+    title: String,
+    class: String,
+) -> Result<MatchExplanation, String> {
+    let subject = MatchSubject {
+        title,
+        class,
+        ..cached_subject(pid).ok_or_else(|| format!("Process {} is not running", pid))?
+    };
+
+    let matched: Vec<bool> = profiles
+        .iter()
+        .map(|p| p.matcher().matches(&subject) && p.conditions.without_window().matches(&subject))
+        .collect();
+    let rules = profiles
+        .iter()
+        .enumerate()
+        .map(|(index, profile)| RuleVerdict {
+            index,
+            process: profile.process.clone(),
+            matcher: profile.matcher().kind(),
+            matched: matched[index],
+            window_unchecked: profile.conditions.needs_window(),
+            rank: profile.rank(),
+        })
+        .collect();
+
+    // Same order as `best_profile`, over the profiles that matched above.
+    let best = |skip: Option<usize>| {
+        profiles
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| matched[*i] && Some(*i) != skip)
+            .max_by_key(|(i, p)| (p.rank(), Reverse(*i)))
+    };
+    let winner = best(None);
+    let reason = match winner {
+        None => "No profile matches".to_string(),
+        Some((index, profile)) => win_reason(profile, best(Some(index)).map(|(_, p)| p)),
+    };
+
+    Ok(MatchExplanation {
+        name: subject.name.clone(),
+        path: subject.path.map(|p| p.to_string_lossy().into_owned()),
+        rules,
+        winner: winner.map(|(i, _)| i),
+        reason,
+    })
+}
+
+fn win_reason(winner: &ProfileConfig, runner_up: Option<&ProfileConfig>) -> String {
+    let Some(other) = runner_up else {
+        return "Only profile that matches".to_string();
+    };
+    let (won, lost) = (winner.rank(), other.rank());
+    if won.priority != lost.priority {
+        format!(
+            "Priority {} beats {} of {}",
+            won.priority, lost.priority, other.process
+        )
+    } else if won.specificity != lost.specificity {
+        format!(
+            "Its {} rule is more specific than the {} rule of {}",
+            winner.matcher().kind(),
+            other.matcher().kind(),
+            other.process
+        )
+    } else if won.conditions != lost.conditions {
+        format!(
+            "It has {} conditions against {} for {}",
+            won.conditions, lost.conditions, other.process
+        )
+    } else {
+        format!("Listed before {}, which ranks the same", other.process)
+    }
+}
+
+fn match_subject(sys: &System, process: &Process) -> MatchSubject {
+    let mut ancestors = Vec::new();
+    let mut parent = process.parent().and_then(|pid| sys.process(pid));
+    while let Some(ancestor) = parent.filter(|_| ancestors.len() < MAX_ANCESTORS) {
+        ancestors.push(MatchSubject {
+            name: ancestor.name().to_string_lossy().into_owned(),
+            path: ancestor.exe().map(Path::to_path_buf),
+            ..Default::default()
+        });
+        parent = ancestor.parent().and_then(|pid| sys.process(pid));
+    }
+
+    MatchSubject {
+        name: process.name().to_string_lossy().into_owned(),
+        path: process.exe().map(Path::to_path_buf),
+        // The first entry is the executable itself.
+        args: process
+            .cmd()
+            .iter()
+            .skip(1)
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect(),
+        ancestors,
+        ..Default::default()
+    }
+}
+
+/// Remembers what to revert to, then applies the profile's topology snapshot
+/// if it has one, or its single mode otherwise. Returns the mode that was
+/// applied, which differs from the profile's when its fallback policy kicked in.
+fn apply_profile(
+    state: &mut MonitorState,
+    profile: &ProfileConfig,
+) -> Result<Resolution, DisplayError> {
+    let requested = Resolution::from(profile.clone());
+    if let Some(topology) = &profile.topology {
+        if state.original_topology.is_none() {
+            state.original_topology = Some(get_topology()?);
+        }
+        record_journal(state, &profile.process, profile.persistence);
+        state.persistence = profile.persistence;
+        apply_topology(topology, profile.persistence)?;
+        return Ok(requested);
+    }
+
+    let mode = resolve_mode(&requested, profile.fallback)?;
+    // Only the first profile to touch a display captures its original mode;
+    // later profiles would capture a mode an earlier one set.
+    if let Some(display) = resolve_display(mode.display.as_ref()) {
+        if let Entry::Vacant(entry) = state
+            .original_resolutions
+            .entry(display.device_name.clone())
+        {
+            if let Some(current) = get_current_resolution(Some(&display.target())) {
+                entry.insert(current);
+            }
+        }
     }
+    record_journal(state, &profile.process, profile.persistence);
+    // Set before the change, since a failed change may still have touched the
+    // display and the revert has to match it
+    state.persistence = profile.persistence;
+    change_resolution(mode.clone(), profile.persistence)?;
+    Ok(mode)
 }
 
-// Shared logic for both Hook and Polling
-fn check_and_apply_window(hwnd: HWND, source: &str) {
-    if hwnd.0.is_null() {
+/// Saves what a revert would go back to before the displays are touched, so
+/// the next start can restore it if this session never gets to revert.
+fn record_journal(state: &MonitorState, process: &str, persistence: Persistence) {
+    let Some(app) = APP_HANDLE.get() else {
         return;
+    };
+    let entry = journal::Journal {
+        process: process.to_string(),
+        original_resolutions: state.original_resolutions.values().cloned().collect(),
+        original_topology: state.original_topology.clone(),
+        persistence,
+    };
+    if let Err(e) = journal::write(app, &entry) {
+        log::error!("Failed to write display journal: {}", e);
     }
+}
 
-    unsafe {
-        if IsIconic(hwnd).as_bool() {
-            return;
+type StateGuard<'a> = MutexGuard<'a, MonitorState>;
+
+/// Applies a profile, trying once more after a short pause when the backend
+/// failed for a transient reason. Definite refusals (unsupported mode, restart
+/// required, display not connected) are given up on right away. The state is
+/// unlocked during the pause so the mouse lock and other events carry on; the
+/// retry is dropped if another profile was applied or reverted meanwhile.
+fn apply_profile_with_retry<'a>(
+    state_arc: &'a Mutex<MonitorState>,
+    mut state: StateGuard<'a>,
+    profile: &ProfileConfig,
+) -> (StateGuard<'a>, Result<Resolution, DisplayError>) {
+    match apply_profile(&mut state, profile) {
+        Err(e) if e.is_retryable() => {
+            log::warn!("Display change failed ({}), retrying once", e);
+            let active = state.active_profile.clone();
+            drop(state);
+            std::thread::sleep(RETRY_DELAY);
+
+            let mut state = state_arc.lock().unwrap();
+            if state.active_profile != active {
+                log::info!("Displays changed hands meanwhile, not retrying");
+                return (state, Err(e));
+            }
+            let result = apply_profile(&mut state, profile);
+            (state, result)
         }
+        result => (state, result),
     }
+}
 
-    let mut process_id = 0;
-    unsafe {
-        GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+/// "WxH@Hz" as the desktop ends up, i.e. portrait for sideways rotations.
+fn mode_label(res: &Resolution) -> String {
+    let (width, height) = res.desktop_size();
+    format!("{}x{}@{}Hz", width, height, res.frequency)
+}
+
+/// Shrinks a window rect by the configured mouse lock padding.
+fn padded_rect(rect: WindowRect, padding: (u32, u32)) -> WindowRect {
+    WindowRect {
+        left: rect.left + padding.0 as i32,
+        top: rect.top + padding.1 as i32,
+        right: rect.right - padding.0 as i32,
+        bottom: rect.bottom - padding.1 as i32,
     }
+}
 
-    let mut sys = System::new();
-    sys.refresh_processes(ProcessesToUpdate::All, true);
+// Shared logic for every foreground source
+fn check_and_apply_window(event: &ForegroundEvent, source: &str) {
+    let process_id = event.pid;
+    if process_id == 0 || SHUTTING_DOWN.load(Ordering::SeqCst) {
+        return;
+    }
 
-    let process_name = if let Some(proc) = sys.process(Pid::from_u32(process_id)) {
-        proc.name().to_string_lossy().into_owned()
-    } else {
+    let Some(subject) = cached_subject(process_id).map(|subject| MatchSubject {
+        title: event.title.clone(),
+        class: event.class.clone(),
+        window: window_state(event.window),
+        ..subject
+    }) else {
         return;
     };
+    let process_name = subject.name.clone();
 
     log::info!(
-        "[{}] Foreground Process: {} (PID: {})",
+        "[{}] Foreground Process: {} (PID: {}, Title: {:?}, Class: {:?})",
         source,
         process_name,
-        process_id
+        process_id,
+        event.title,
+        event.class
     );
 
-    let app_handle = unsafe {
-        if let Some(ref h) = APP_HANDLE {
-            h.clone()
-        } else {
-            return;
-        }
+    let app_handle = if let Some(h) = APP_HANDLE.get() {
+        h.clone()
+    } else {
+        return;
     };
 
     // Load Config
     let config = crate::config_manager::get_config(app_handle.clone());
-    let profiles = config.automation.auto_res.profiles; // Vec<ProfileConfig>
+    // Start and exit triggered profiles are handled by the process watcher.
+    let profiles: Vec<ProfileConfig> = config
+        .automation
+        .auto_res
+        .profiles
+        .into_iter()
+        .filter(|p| p.trigger == Trigger::WhileFocused)
+        .collect();
     let mouse_lock_list = config.automation.mouse_lock;
 
     let state_arc = {
@@ -128,23 +416,9 @@ fn check_and_apply_window(hwnd: HWND, source: &str) {
         let mut state = state_arc.lock().unwrap();
 
         // Check for Mouse Lock
-        let mut should_lock_mouse = false;
-        let mut padding = (0, 0);
-        let proc_lower = process_name.to_lowercase();
-        let proc_stem = std::path::Path::new(&process_name)
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .unwrap_or(&process_name)
-            .to_lowercase();
-
-        for lock_config in &mouse_lock_list {
-            let target_lower = lock_config.process.to_lowercase();
-            if proc_lower == target_lower || proc_stem == target_lower {
-                should_lock_mouse = true;
-                padding = (lock_config.padding_x, lock_config.padding_y);
-                break;
-            }
-        }
+        let lock_config = mouse_lock_list.iter().find(|lock| lock.matches(&subject));
+        let should_lock_mouse = lock_config.is_some();
+        let padding = lock_config.map_or((0, 0), |lock| (lock.padding_x, lock.padding_y));
 
         if should_lock_mouse {
             log::info!(
@@ -154,32 +428,30 @@ fn check_and_apply_window(hwnd: HWND, source: &str) {
                 padding.0,
                 padding.1
             );
-            unsafe {
-                let mut rect = RECT::default();
-                if GetWindowRect(hwnd, &mut rect).is_ok() {
-                    rect.left += padding.0 as i32;
-                    rect.top += padding.1 as i32;
-                    rect.right -= padding.0 as i32;
-                    rect.bottom -= padding.1 as i32;
-                    let _ = ClipCursor(Some(&rect));
-                    state.locked_window = Some(hwnd.0 as usize);
-                    state.locked_window_padding = padding;
-
-                    if state.locked_process.as_deref() != Some(&process_name) {
-                        state.locked_process = Some(process_name.clone());
-                        let _ = app_handle.emit(
-                            "mouse-lock-changed",
-                            serde_json::json!({
-                                "process": process_name,
-                                "status": "active"
-                            }),
-                        );
-                    }
+            let window_source = foreground::source();
+            if let Some(rect) = window_source.and_then(|s| s.window_rect(event.window)) {
+                let confined = window_source
+                    .is_some_and(|s| s.confine_cursor(Some(padded_rect(rect, padding))));
+                if !confined && !MOUSE_LOCK_WARNED.swap(true, Ordering::Relaxed) {
+                    log::warn!("[{}] Mouse lock is not supported on this platform", source);
+                }
+                state.locked_window = Some(event.window);
+                state.locked_window_padding = padding;
+
+                if state.locked_process.as_deref() != Some(&process_name) {
+                    state.locked_process = Some(process_name.clone());
+                    let _ = app_handle.emit(
+                        "mouse-lock-changed",
+                        serde_json::json!({
+                            "process": process_name,
+                            "status": "active"
+                        }),
+                    );
                 }
             }
         } else {
-            unsafe {
-                let _ = ClipCursor(None);
+            if let Some(window_source) = foreground::source() {
+                window_source.confine_cursor(None);
             }
             state.locked_window = None;
 
@@ -195,53 +467,72 @@ fn check_and_apply_window(hwnd: HWND, source: &str) {
         }
 
         // Check for Resolution Profile
-        let mut matched_profile: Option<(String, Resolution)> = None;
-
-        for profile in profiles {
-            let key = profile.process.clone();
-            let key_lower = key.to_lowercase();
-
-            // 1. Exact Match
-            if proc_lower == key_lower {
-                matched_profile = Some((key, profile.into()));
-                break;
-            }
+        let matched_profile = best_profile(&profiles, &subject).map(|(_, p)| p.clone());
 
-            // 2. Stem Match
-            if proc_stem == key_lower {
-                matched_profile = Some((key, profile.into()));
-                break;
-            }
+        state.foreground_profile = matched_profile.clone();
+        // A profile kept by a running process is not affected by focus.
+        if state
+            .active_profile
+            .as_ref()
+            .is_some_and(|p| p.trigger != Trigger::WhileFocused)
+        {
+            return;
         }
-
-        if let Some((profile_name, profile_res)) = matched_profile {
+        if let Some(profile) = matched_profile {
+            let profile_name = profile.process.clone();
+            let profile_res = Resolution::from(profile.clone());
             // We are inside a target process
             let was_revert_pending = state.revert_pending.is_some();
             state.revert_pending = None;
 
-            if state.active_process.as_deref() != Some(&profile_name) {
+            if state.active_profile.as_ref() != Some(&profile) {
                 log::info!(
                     "[{}] MATCH! Changing resolution for: {}",
                     source,
                     profile_name
                 );
 
-                if state.original_resolution.is_none() {
-                    if let Some(current) = get_current_resolution() {
-                        state.original_resolution = Some(current.clone());
+                let (guard, result) = apply_profile_with_retry(&state_arc, state, &profile);
+                state = guard;
+                match result {
+                    Ok(applied) => {
+                        state.active_profile = Some(profile.clone());
+                        state.active_pid = Some(process_id);
+                        log::info!("Resolution Set!");
+                        let _ = app_handle.emit(
+                            "resolution-changed",
+                            serde_json::json!({
+                                "process": profile_name,
+                                "resolution": mode_label(&applied),
+                                "requested": mode_label(&profile_res),
+                                "fallback": !applied.same_mode(&profile_res),
+                                "status": "changed"
+                            }),
+                        );
+                        state.active_mode = Some(applied);
+                    }
+                    Err(DisplayError::DeviceNotFound { device }) => {
+                        log::warn!("[{}] Display not connected: {}", source, device);
+                        let _ = app_handle.emit(
+                            "resolution-changed",
+                            serde_json::json!({
+                                "process": profile_name,
+                                "display": device,
+                                "status": "display-not-connected"
+                            }),
+                        );
+                    }
+                    Err(e) => {
+                        log::error!("Failed to set resolution: {}", e);
+                        let _ = app_handle.emit(
+                            "resolution-changed",
+                            serde_json::json!({
+                                "process": profile_name,
+                                "status": "failed",
+                                "error": e
+                            }),
+                        );
                     }
-                }
-
-                if let Err(e) = change_resolution(profile_res.clone()) {
-                    log::error!("Failed to set resolution: {}", e);
-                } else {
-                    state.active_process = Some(profile_name.clone());
-                    log::info!("Resolution Set!");
-                    let _ = app_handle.emit("resolution-changed", serde_json::json!({
-                        "process": profile_name,
-                        "resolution": format!("{}x{}@{}Hz", profile_res.width, profile_res.height, profile_res.frequency),
-                        "status": "changed"
-                    }));
                 }
             } else if was_revert_pending {
                 log::info!(
@@ -249,15 +540,21 @@ fn check_and_apply_window(hwnd: HWND, source: &str) {
                     source,
                     profile_name
                 );
-                let _ = app_handle.emit("resolution-changed", serde_json::json!({
-                    "process": profile_name,
-                    "resolution": format!("{}x{}@{}Hz", profile_res.width, profile_res.height, profile_res.frequency),
-                    "status": "changed"
-                }));
+                let applied = state.active_mode.clone().unwrap_or(profile_res.clone());
+                let _ = app_handle.emit(
+                    "resolution-changed",
+                    serde_json::json!({
+                        "process": profile_name,
+                        "resolution": mode_label(&applied),
+                        "requested": mode_label(&profile_res),
+                        "fallback": !applied.same_mode(&profile_res),
+                        "status": "changed"
+                    }),
+                );
             }
         } else {
             // Not a target process
-            if let Some(active) = &state.active_process {
+            if let Some(active) = state.active_profile.as_ref().map(|p| &p.process) {
                 if state.revert_pending.is_none() {
                     let active_name = active.clone();
                     log::info!(
@@ -279,45 +576,508 @@ fn check_and_apply_window(hwnd: HWND, source: &str) {
     }
 }
 
-pub fn start_monitor_hook(app: AppHandle) {
-    unsafe {
-        APP_HANDLE = Some(app.clone());
-        *STATE.lock().unwrap() = Some(Arc::new(Mutex::new(MonitorState {
-            original_resolution: None,
-            active_process: None,
-            revert_pending: None,
-            locked_window: None,
-            locked_window_padding: (0, 0),
-            locked_process: None,
-        })));
-    }
-
-    // Thread 1: Windows Event Hook
-    std::thread::spawn(|| {
-        println!("Starting Event Hook Thread...");
-        unsafe {
-            let hook = SetWinEventHook(
-                EVENT_SYSTEM_FOREGROUND,
-                EVENT_SYSTEM_FOREGROUND,
-                None,
-                Some(win_event_hook),
-                0,
-                0,
-                WINEVENT_OUTOFCONTEXT,
-            );
+/// Restores the topology captured before the first topology profile, then the
+/// default profile or the original mode, and forgets the active profile. What
+/// could not be restored stays remembered and journaled, so the next revert or
+/// the recovery at startup tries it again.
+fn revert_displays(app: &AppHandle, state: &mut MonitorState) {
+    let default_profile = crate::config_manager::get_config(app.clone())
+        .automation
+        .auto_res
+        .default_profile;
+    let process = state
+        .active_profile
+        .as_ref()
+        .map(|p| p.process.clone())
+        .unwrap_or_default();
 
-            if hook.0.is_null() {
-                eprintln!("Failed to set SetWinEventHook");
-                return;
+    // Put the whole layout back first; a default profile or
+    // original mode is applied on top of it.
+    let mut failed = false;
+    let restored_topology = match state.original_topology.take() {
+        Some(topology) => match apply_topology(&topology, state.persistence) {
+            Ok(()) => true,
+            Err(e) => {
+                log::error!("Failed to restore display topology: {}", e);
+                state.original_topology = Some(topology);
+                failed = true;
+                false
             }
+        },
+        None => false,
+    };
 
-            let mut msg = MSG::default();
-            while GetMessageW(&mut msg, None, 0, 0).as_bool() {
-                let _ = TranslateMessage(&msg);
-                let _ = DispatchMessageW(&msg);
+    // The default profile stands in for the original mode of the display it
+    // targets; every other display changed by a profile gets its own back.
+    let mut originals = std::mem::take(&mut state.original_resolutions);
+    let default_device = default_profile
+        .as_ref()
+        .and_then(|def| resolve_display(def.display.as_ref()))
+        .map(|d| d.device_name);
+    // Each with the device whose original it puts back.
+    let mut targets: Vec<(Option<String>, Resolution)> = Vec::new();
+    if let Some(def) = default_profile.filter(|_| {
+        originals.is_empty()
+            || default_device
+                .as_ref()
+                .is_some_and(|d| originals.contains_key(d))
+    }) {
+        log::info!(
+            "Using Default Profile: {}x{}@{}",
+            def.width,
+            def.height,
+            def.frequency
+        );
+        // Undo a rotation or scaling the profile changed
+        // unless the default profile pins its own.
+        let original = default_device
+            .as_ref()
+            .and_then(|d| originals.get(d))
+            .cloned()
+            .unwrap_or_default();
+        targets.push((
+            default_device.clone(),
+            Resolution {
+                rotation: def.rotation.or(original.rotation),
+                scaling: def.scaling.or(original.scaling),
+                ..def
+            },
+        ));
+    }
+    let replaced = (!targets.is_empty()).then_some(default_device).flatten();
+    targets.extend(
+        originals
+            .iter()
+            .filter(|(device, _)| replaced.as_ref() != Some(*device))
+            .map(|(device, res)| (Some(device.clone()), res.clone())),
+    );
+
+    let mut reverted = restored_topology;
+    for (device, res) in targets {
+        match change_resolution(res, state.persistence) {
+            Ok(()) => {
+                reverted = true;
+                if let Some(device) = device {
+                    originals.remove(&device);
+                }
+            }
+            Err(e) => {
+                log::error!("Failed to revert: {}", e);
+                failed = true;
             }
         }
-    });
+    }
+    if reverted && !failed {
+        log::info!("Reverted successfully.");
+        let _ = app.emit(
+            "resolution-changed",
+            serde_json::json!({ "status": "reverted" }),
+        );
+    } else if !reverted && !failed {
+        log::warn!("No resolution to revert to.");
+    }
+
+    state.active_profile = None;
+    state.active_pid = None;
+    state.active_mode = None;
+    if failed {
+        state.original_resolutions = originals;
+        record_journal(state, &process, state.persistence);
+    } else {
+        state.persistence = Persistence::default();
+        journal::clear(app);
+    }
+}
+
+/// Reverts the active profile, then hands the displays to a running process
+/// whose profile holds while it runs.
+fn revert_and_resume<'a>(
+    app: &AppHandle,
+    state_arc: &'a Mutex<MonitorState>,
+    mut state: StateGuard<'a>,
+) -> StateGuard<'a> {
+    revert_displays(app, &mut state);
+    apply_running_profile(app, state_arc, state)
+}
+
+fn same_monitor(a: &DisplayInfo, b: &DisplayInfo) -> bool {
+    a.connector == b.connector && a.monitor == b.monitor
+}
+
+/// Compares the connected displays with the last known set. When a monitor
+/// was added (turned back on, KVM switch, docking) while the foreground process
+/// has a profile, the profile is applied again: the OS resets the mode of a
+/// returning monitor, and a profile whose display was missing gets its first
+/// chance to apply. Returns whether the set of displays changed.
+fn handle_hotplug(app: &AppHandle) -> bool {
+    if SHUTTING_DOWN.load(Ordering::SeqCst) {
+        return false;
+    }
+    let state_arc = {
+        let guard = STATE.lock().unwrap();
+        guard.clone()
+    };
+    let Some(state_arc) = state_arc else {
+        return false;
+    };
+    let mut state = state_arc.lock().unwrap();
+
+    let displays = get_displays();
+    let added: Vec<String> = displays
+        .iter()
+        .filter(|d| !state.displays.iter().any(|k| same_monitor(k, d)))
+        .map(|d| d.device_name.clone())
+        .collect();
+    let removed: Vec<String> = state
+        .displays
+        .iter()
+        .filter(|k| !displays.iter().any(|d| same_monitor(k, d)))
+        .map(|k| k.device_name.clone())
+        .collect();
+    if added.is_empty() && removed.is_empty() {
+        return false;
+    }
+    state.displays = displays;
+    log::info!(
+        "Displays changed (added: {:?}, removed: {:?})",
+        added,
+        removed
+    );
+
+    let mut reapplied = None;
+    let mut error = None;
+    let running_profile = state
+        .active_profile
+        .clone()
+        .filter(|p| p.trigger == Trigger::WhileRunning);
+    if let Some(profile) = state
+        .foreground_profile
+        .clone()
+        .or(running_profile)
+        .filter(|_| !added.is_empty())
+    {
+        let already_applied = state.active_profile.as_ref() == Some(&profile);
+        // Topology profiles have no single mode to compare against.
+        let drifted = match &state.active_mode {
+            Some(active) if profile.topology.is_none() => {
+                get_current_resolution(active.display.as_ref())
+                    .is_some_and(|current| !current.same_mode(active))
+            }
+            _ => true,
+        };
+        if !already_applied || drifted {
+            log::info!("Re-applying profile for {}", profile.process);
+            let (guard, result) = apply_profile_with_retry(&state_arc, state, &profile);
+            state = guard;
+            match result {
+                Ok(applied) => {
+                    state.active_profile = Some(profile.clone());
+                    state.active_mode = Some(applied);
+                    reapplied = Some(profile.process.clone());
+                }
+                Err(e) => {
+                    log::error!("Failed to re-apply profile: {}", e);
+                    error = Some(e);
+                }
+            }
+        }
+    }
+
+    let _ = app.emit(
+        "display-hotplug",
+        serde_json::json!({
+            "added": added,
+            "removed": removed,
+            "process": reapplied,
+            "resolution": state.active_mode.as_ref().filter(|_| reapplied.is_some()).map(mode_label),
+            "error": error
+        }),
+    );
+    true
+}
+
+/// Reconciles the remembered modes after a display notification. Changes made
+/// by kortex itself leave the display in the active profile's mode; anything
+/// else while a profile is active was done by the user or the game and is
+/// handled according to the configured policy.
+fn handle_display_change(app: &AppHandle) {
+    if SHUTTING_DOWN.load(Ordering::SeqCst) {
+        return;
+    }
+    let state_arc = {
+        let guard = STATE.lock().unwrap();
+        guard.clone()
+    };
+    let Some(state_arc) = state_arc else {
+        return;
+    };
+    let mut state = state_arc.lock().unwrap();
+
+    // Topology profiles have no single mode to compare against.
+    let active_device = state
+        .active_mode
+        .as_ref()
+        .and_then(|active| resolve_display(active.display.as_ref()))
+        .map(|d| d.device_name)
+        .filter(|device| state.original_resolutions.contains_key(device));
+    let external = match (&state.active_mode, &active_device) {
+        (Some(active), Some(_)) => get_current_resolution(active.display.as_ref())
+            .filter(|current| !current.same_mode(active)),
+        _ => None,
+    };
+
+    let policy = crate::config_manager::get_config(app.clone())
+        .automation
+        .auto_res
+        .external_change;
+    if let (Some(current), Some(process)) = (
+        &external,
+        state.active_profile.as_ref().map(|p| p.process.clone()),
+    ) {
+        log::info!(
+            "Display changed to {} outside of the profile for {} ({:?})",
+            mode_label(current),
+            process,
+            policy
+        );
+        match policy {
+            ExternalChangePolicy::KeepOriginal => {}
+            ExternalChangePolicy::UpdateOriginal => {
+                if let Some(device) = active_device {
+                    let original = Resolution {
+                        display: state.original_resolutions[&device].display.clone(),
+                        ..current.clone()
+                    };
+                    state.original_resolutions.insert(device, original);
+                }
+                state.active_mode = Some(current.clone());
+                record_journal(&state, &process, state.persistence);
+            }
+            ExternalChangePolicy::ForgetProfile => {
+                state.foreground_profile = None;
+                state.active_profile = None;
+                state.active_pid = None;
+                state.active_mode = None;
+                state.original_resolutions.clear();
+                state.original_topology = None;
+                state.persistence = Persistence::default();
+                state.revert_pending = None;
+                journal::clear(app);
+            }
+        }
+    }
+
+    let _ = app.emit(
+        "display-changed",
+        serde_json::json!({
+            "external": external.is_some(),
+            "resolution": external.as_ref().map(mode_label),
+            "policy": policy
+        }),
+    );
+}
+
+/// Applies start-triggered profiles and reverts any profile whose process
+/// exited, without waiting for a focus change.
+fn handle_process_event(app: &AppHandle, event: ProcessEvent) {
+    match event {
+        ProcessEvent::Started { pid, .. } | ProcessEvent::Exited { pid } => {
+            process_cache::invalidate(pid)
+        }
+    }
+    if SHUTTING_DOWN.load(Ordering::SeqCst) {
+        return;
+    }
+    let state_arc = {
+        let guard = STATE.lock().unwrap();
+        guard.clone()
+    };
+    let Some(state_arc) = state_arc else {
+        return;
+    };
+    let mut state = state_arc.lock().unwrap();
+
+    match event {
+        ProcessEvent::Exited { pid } => {
+            if state.active_pid != Some(pid) {
+                return;
+            }
+            let Some(profile) = state.active_profile.clone() else {
+                return;
+            };
+            // Another instance matching the same lifecycle rule keeps the displays.
+            if profile.trigger != Trigger::WhileFocused {
+                let other = process_watcher::running()
+                    .into_iter()
+                    .find(|&other| cached_subject(other).is_some_and(|s| profile.matches(&s)));
+                if let Some(other) = other {
+                    log::info!(
+                        "{} exited (PID: {}). Keeping it for PID {}",
+                        profile.process,
+                        pid,
+                        other
+                    );
+                    state.active_pid = Some(other);
+                    return;
+                }
+            }
+            log::info!("{} exited. Reverting now.", profile.process);
+            state.revert_pending = None;
+            state.foreground_profile = None;
+            drop(revert_and_resume(app, &state_arc, state));
+        }
+        ProcessEvent::Started { pid, initial } => {
+            // Whatever is active already keeps the displays; the start is
+            // looked at again once that profile is reverted.
+            if state.active_profile.is_some() {
+                return;
+            }
+            let profiles = lifecycle_profiles(app, !initial);
+            if profiles.is_empty() {
+                return;
+            }
+            let Some(subject) = cached_subject(pid) else {
+                return;
+            };
+            if let Some((_, profile)) = best_profile(&profiles, &subject) {
+                log::info!(
+                    "{} started (PID: {}). Changing resolution for: {}",
+                    subject.name,
+                    pid,
+                    profile.process
+                );
+                drop(apply_lifecycle_profile(
+                    app, &state_arc, state, pid, profile,
+                ));
+            }
+        }
+    }
+}
+
+/// Profiles that follow a process's lifetime rather than its focus. `OnLaunch`
+/// ones are left out for processes that were running before the watcher.
+fn lifecycle_profiles(app: &AppHandle, launched: bool) -> Vec<ProfileConfig> {
+    crate::config_manager::get_config(app.clone())
+        .automation
+        .auto_res
+        .profiles
+        .into_iter()
+        .filter(|p| match p.trigger {
+            Trigger::WhileFocused => false,
+            Trigger::WhileRunning => true,
+            Trigger::OnLaunch => launched,
+        })
+        .collect()
+}
+
+/// After a revert, hands the displays to a running process whose profile holds
+/// while it runs, as that process's start was skipped or its profile replaced.
+fn apply_running_profile<'a>(
+    app: &AppHandle,
+    state_arc: &'a Mutex<MonitorState>,
+    state: StateGuard<'a>,
+) -> StateGuard<'a> {
+    if SHUTTING_DOWN.load(Ordering::SeqCst) || state.active_profile.is_some() {
+        return state;
+    }
+    let profiles = lifecycle_profiles(app, false);
+    if profiles.is_empty() {
+        return state;
+    }
+    let best = process_watcher::running()
+        .into_iter()
+        .filter_map(|pid| {
+            let subject = cached_subject(pid)?;
+            let (index, profile) = best_profile(&profiles, &subject)?;
+            Some((pid, index, profile))
+        })
+        .max_by_key(|(_, index, profile)| (profile.rank(), Reverse(*index)));
+    if let Some((pid, _, profile)) = best {
+        log::info!(
+            "{} is still running (PID: {}). Changing resolution again",
+            profile.process,
+            pid
+        );
+        return apply_lifecycle_profile(app, state_arc, state, pid, profile);
+    }
+    state
+}
+
+fn apply_lifecycle_profile<'a>(
+    app: &AppHandle,
+    state_arc: &'a Mutex<MonitorState>,
+    state: StateGuard<'a>,
+    pid: u32,
+    profile: &ProfileConfig,
+) -> StateGuard<'a> {
+    let profile_res = Resolution::from(profile.clone());
+    let (mut state, result) = apply_profile_with_retry(state_arc, state, profile);
+    match result {
+        Ok(applied) => {
+            state.active_profile = Some(profile.clone());
+            state.active_pid = Some(pid);
+            let _ = app.emit(
+                "resolution-changed",
+                serde_json::json!({
+                    "process": profile.process,
+                    "resolution": mode_label(&applied),
+                    "requested": mode_label(&profile_res),
+                    "fallback": !applied.same_mode(&profile_res),
+                    "status": "changed"
+                }),
+            );
+            state.active_mode = Some(applied);
+        }
+        Err(e) => {
+            log::error!("Failed to set resolution: {}", e);
+            let _ = app.emit(
+                "resolution-changed",
+                serde_json::json!({
+                    "process": profile.process,
+                    "status": "failed",
+                    "error": e
+                }),
+            );
+        }
+    }
+    state
+}
+
+pub fn start_monitor_hook(app: AppHandle) {
+    let _ = APP_HANDLE.set(app.clone());
+    *STATE.lock().unwrap() = Some(Arc::new(Mutex::new(MonitorState {
+        original_resolutions: HashMap::new(),
+        original_topology: None,
+        active_profile: None,
+        active_pid: None,
+        active_mode: None,
+        persistence: Persistence::Temporary,
+        revert_pending: None,
+        locked_window: None,
+        locked_window_padding: (0, 0),
+        locked_process: None,
+        foreground_profile: None,
+        displays: get_displays(),
+    })));
+
+    // Thread 1: Foreground Window Source
+    if let Some(window_source) = foreground::source() {
+        std::thread::spawn(move || {
+            println!(
+                "Starting Foreground Source Thread ({})...",
+                window_source.name()
+            );
+            let name = window_source.name();
+            let result = window_source.run(Box::new(move |event| {
+                check_and_apply_window(&event, name);
+            }));
+            if let Err(e) = result {
+                eprintln!("Foreground source stopped: {}", e);
+            }
+        });
+    } else {
+        log::warn!("Foreground tracking is unavailable, automatic profiles are disabled");
+    }
 
     // Thread 2: Revert Timer Watcher
     let app_handle_thread = app.clone();
@@ -325,6 +1085,9 @@ pub fn start_monitor_hook(app: AppHandle) {
         println!("Starting Revert Watcher Thread...");
         loop {
             std::thread::sleep(Duration::from_secs(1));
+            if SHUTTING_DOWN.load(Ordering::SeqCst) {
+                break;
+            }
 
             let state_arc = {
                 let guard = STATE.lock().unwrap();
@@ -337,40 +1100,12 @@ pub fn start_monitor_hook(app: AppHandle) {
                 if let Some(pending_time) = state.revert_pending {
                     let config = crate::config_manager::get_config(app_handle_thread.clone());
                     let delay_ms = config.automation.auto_res.revert_delay;
-                    let default_profile = config.automation.auto_res.default_profile;
 
                     if pending_time.elapsed() > Duration::from_millis(delay_ms) {
-                        if let Some(active) = &state.active_process {
+                        if let Some(active) = state.active_profile.as_ref().map(|p| &p.process) {
                             log::info!("Revert timer expired for {}. Reverting now.", active);
 
-                            let target = if let Some(def) = default_profile {
-                                log::info!(
-                                    "Using Default Profile: {}x{}@{}",
-                                    def.width,
-                                    def.height,
-                                    def.frequency
-                                );
-                                Some(def)
-                            } else {
-                                state.original_resolution.clone()
-                            };
-
-                            if let Some(res) = target {
-                                if let Err(e) = change_resolution(res) {
-                                    log::error!("Failed to revert: {}", e);
-                                } else {
-                                    log::info!("Reverted successfully.");
-                                    let _ = app_handle_thread.emit(
-                                        "resolution-changed",
-                                        serde_json::json!({ "status": "reverted" }),
-                                    );
-                                }
-                            } else {
-                                log::warn!("No resolution to revert to.");
-                            }
-
-                            state.active_process = None;
-                            state.original_resolution = None;
+                            state = revert_and_resume(&app_handle_thread, &state_arc, state);
                         }
                         state.revert_pending = None;
                     }
@@ -379,39 +1114,52 @@ pub fn start_monitor_hook(app: AppHandle) {
         }
     });
 
-    // Thread 3: Mouse Lock Enforcement (High Frequency)
-    std::thread::spawn(move || {
-        println!("Starting Mouse Lock Watcher Thread...");
-        loop {
-            std::thread::sleep(Duration::from_millis(20));
+    // Process starts and exits (dispatched on their own thread)
+    let app_handle_processes = app.clone();
+    process_watcher::watch(Box::new(move |event| {
+        handle_process_event(&app_handle_processes, event);
+    }));
 
-            let state_arc = {
-                let guard = STATE.lock().unwrap();
-                guard.clone()
-            };
+    // Display change notifications (dispatched on their own thread)
+    let app_handle_events = app.clone();
+    display_events::watch(Box::new(move |event| {
+        // A monitor coming back also changes modes; that is not the user's doing.
+        if !handle_hotplug(&app_handle_events) && event == DisplayEvent::ModeChanged {
+            handle_display_change(&app_handle_events);
+        }
+    }));
 
-            if let Some(state_arc) = state_arc {
-                let (target_hwnd, padding) = {
-                    let state = state_arc.lock().unwrap();
-                    (state.locked_window, state.locked_window_padding)
+    // Thread 3: Mouse Lock Enforcement (High Frequency)
+    if let Some(window_source) = foreground::source() {
+        std::thread::spawn(move || {
+            println!("Starting Mouse Lock Watcher Thread...");
+            loop {
+                std::thread::sleep(Duration::from_millis(20));
+
+                let state_arc = {
+                    let guard = STATE.lock().unwrap();
+                    guard.clone()
                 };
 
-                if let Some(hwnd_val) = target_hwnd {
-                    let hwnd = HWND(hwnd_val as *mut _);
-                    unsafe {
-                        let mut rect = RECT::default();
-                        if GetWindowRect(hwnd, &mut rect).is_ok() {
-                            rect.left += padding.0 as i32;
-                            rect.top += padding.1 as i32;
-                            rect.right -= padding.0 as i32;
-                            rect.bottom -= padding.1 as i32;
-                            let _ = ClipCursor(Some(&rect));
+                if let Some(state_arc) = state_arc {
+                    // Confine while holding the state so shutdown cannot release
+                    // the cursor in between.
+                    let state = state_arc.lock().unwrap();
+                    if SHUTTING_DOWN.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    if let Some(window) = state.locked_window {
+                        if let Some(rect) = window_source.window_rect(window) {
+                            window_source.confine_cursor(Some(padded_rect(
+                                rect,
+                                state.locked_window_padding,
+                            )));
                         }
                     }
                 }
             }
-        }
-    });
+        });
+    }
 }
 
 pub fn force_revert() {
@@ -429,3 +1177,35 @@ pub fn force_revert() {
         }
     }
 }
+
+/// Releases the cursor and reverts an active profile before the app exits.
+/// Safe to call more than once; only the first call does anything.
+pub fn shutdown() {
+    if SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let state_arc = {
+        let guard = STATE.lock().unwrap();
+        guard.clone()
+    };
+    let Some(state_arc) = state_arc else {
+        return;
+    };
+    let mut state = state_arc.lock().unwrap();
+
+    state.revert_pending = None;
+    state.locked_process = None;
+    if state.locked_window.take().is_some() {
+        if let Some(source) = foreground::source() {
+            source.confine_cursor(None);
+        }
+    }
+
+    if let Some(active) = state.active_profile.as_ref().map(|p| p.process.clone()) {
+        log::info!("Shutting down, reverting profile for {}", active);
+        if let Some(app) = APP_HANDLE.get() {
+            revert_displays(app, &mut state);
+        }
+    }
+}
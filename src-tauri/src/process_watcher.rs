@@ -0,0 +1,90 @@
+use std::collections::BTreeSet;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+
+#[cfg(target_os = "linux")]
+mod proc;
+#[cfg(target_os = "windows")]
+mod win32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessEvent {
+    /// A process appeared. `initial` is set for the processes already running
+    /// when the watcher started.
+    Started {
+        pid: u32,
+        initial: bool,
+    },
+    Exited {
+        pid: u32,
+    },
+}
+
+pub type ProcessEventHandler = Box<dyn FnMut(ProcessEvent) + Send>;
+
+// Processes reported as started and not yet as exited
+static RUNNING: Mutex<BTreeSet<u32>> = Mutex::new(BTreeSet::new());
+
+/// Starts reporting process starts and exits: WMI process traces on Windows,
+/// and on Linux a poll of the `/proc` listing, since the netlink proc connector
+/// needs `CAP_NET_ADMIN`. `handler` runs on its own thread. A start already
+/// reported is dropped, so a listener may overlap its snapshot of the running
+/// processes with live events.
+pub fn watch(handler: ProcessEventHandler) {
+    let (tx, rx) = mpsc::channel();
+    if !listen(tx) {
+        log::warn!("Process start and exit events are unavailable");
+        return;
+    }
+
+    std::thread::spawn(move || {
+        println!("Starting Process Event Thread...");
+        dispatch(rx, handler);
+    });
+}
+
+/// PIDs of the processes currently running, as far as the watcher has seen.
+pub fn running() -> Vec<u32> {
+    RUNNING.lock().unwrap().iter().copied().collect()
+}
+
+fn dispatch(rx: Receiver<ProcessEvent>, mut handler: ProcessEventHandler) {
+    while let Ok(event) = rx.recv() {
+        let new = {
+            let mut running = RUNNING.lock().unwrap();
+            match event {
+                ProcessEvent::Started { pid, .. } => running.insert(pid),
+                // Always passed on: the PID may be reused and cached.
+                ProcessEvent::Exited { pid } => {
+                    running.remove(&pid);
+                    true
+                }
+            }
+        };
+        if new {
+            handler(event);
+        }
+    }
+}
+
+fn listen(tx: Sender<ProcessEvent>) -> bool {
+    #[cfg(target_os = "windows")]
+    let result = win32::listen(tx);
+
+    #[cfg(target_os = "linux")]
+    let result = proc::listen(tx);
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    let result: Result<(), String> = {
+        drop(tx);
+        Err("unsupported platform".to_string())
+    };
+
+    match result {
+        Ok(()) => true,
+        Err(e) => {
+            log::warn!("Process watcher unavailable: {}", e);
+            false
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use super::ProcessEvent;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::sync::mpsc::Sender;
+use std::time::Duration;
+
+// How often the PID listing is compared with the previous one
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Polls the numeric entries of `/proc`. Only the directory is listed; the
+/// processes themselves are read by the process cache when a rule needs them.
+/// A process that lives shorter than the poll interval is not seen.
+pub fn listen(tx: Sender<ProcessEvent>) -> Result<(), String> {
+    let mut known = pids().map_err(|e| format!("cannot list /proc: {}", e))?;
+    for &pid in &known {
+        let _ = tx.send(ProcessEvent::Started { pid, initial: true });
+    }
+
+    std::thread::spawn(move || loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let current = match pids() {
+            Ok(current) => current,
+            Err(e) => {
+                log::error!("Failed to list /proc: {}", e);
+                continue;
+            }
+        };
+
+        let exited = known
+            .difference(&current)
+            .map(|&pid| ProcessEvent::Exited { pid });
+        let started = current
+            .difference(&known)
+            .map(|&pid| ProcessEvent::Started {
+                pid,
+                initial: false,
+            });
+        for event in exited.chain(started) {
+            if tx.send(event).is_err() {
+                return;
+            }
+        }
+        known = current;
+    });
+    Ok(())
+}
+
+fn pids() -> io::Result<HashSet<u32>> {
+    Ok(fs::read_dir("/proc")?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect())
+}
@@ -0,0 +1,179 @@
+use std::sync::mpsc::{self, Sender};
+
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+use windows::core::{w, Interface, BSTR, PCWSTR};
+use windows::Win32::System::{
+    Com::{
+        CoCreateInstance, CoInitializeEx, CoSetProxyBlanket, CLSCTX_INPROC_SERVER,
+        COINIT_MULTITHREADED, EOAC_NONE, RPC_C_AUTHN_LEVEL_CALL, RPC_C_IMP_LEVEL_IMPERSONATE,
+    },
+    Rpc::{RPC_C_AUTHN_WINNT, RPC_C_AUTHZ_NONE},
+    Variant::VARIANT,
+    Wmi::{
+        IEnumWbemClassObject, IWbemClassObject, IWbemContext, IWbemLocator, WbemLocator,
+        WBEM_FLAG_FORWARD_ONLY, WBEM_FLAG_RETURN_IMMEDIATELY, WBEM_INFINITE,
+    },
+};
+
+use super::ProcessEvent;
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Start,
+    Stop,
+}
+
+impl Kind {
+    /// Kernel trace events, delivered as they happen but only to administrators.
+    fn trace_query(self) -> &'static str {
+        match self {
+            Kind::Start => "SELECT ProcessID FROM Win32_ProcessStartTrace",
+            Kind::Stop => "SELECT ProcessID FROM Win32_ProcessStopTrace",
+        }
+    }
+
+    /// Instance events, open to everyone; WMI polls for them once a second.
+    fn instance_query(self) -> &'static str {
+        match self {
+            Kind::Start => {
+                "SELECT TargetInstance FROM __InstanceCreationEvent WITHIN 1 \
+                 WHERE TargetInstance ISA 'Win32_Process'"
+            }
+            Kind::Stop => {
+                "SELECT TargetInstance FROM __InstanceDeletionEvent WITHIN 1 \
+                 WHERE TargetInstance ISA 'Win32_Process'"
+            }
+        }
+    }
+
+    fn event(self, pid: u32) -> ProcessEvent {
+        match self {
+            Kind::Start => ProcessEvent::Started {
+                pid,
+                initial: false,
+            },
+            Kind::Stop => ProcessEvent::Exited { pid },
+        }
+    }
+}
+
+/// Subscribes to WMI process start and stop events, then reports the processes
+/// already running. Subscribing first means a process starting in between is
+/// reported twice rather than missed; the dispatcher drops the repeat.
+pub fn listen(tx: Sender<ProcessEvent>) -> Result<(), String> {
+    for kind in [Kind::Start, Kind::Stop] {
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let tx = tx.clone();
+        std::thread::spawn(move || receive(kind, tx, ready_tx));
+        ready_rx
+            .recv()
+            .map_err(|_| "WMI event thread ended".to_string())??;
+    }
+
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+    for pid in sys.processes().keys() {
+        let _ = tx.send(ProcessEvent::Started {
+            pid: pid.as_u32(),
+            initial: true,
+        });
+    }
+    Ok(())
+}
+
+/// Forwards the events of one subscription until the dispatcher goes away.
+/// COM objects stay on the thread that created them, so the subscription is
+/// made here and its outcome sent back through `ready`.
+fn receive(kind: Kind, tx: Sender<ProcessEvent>, ready: Sender<Result<(), String>>) {
+    let (events, traced) = match unsafe { subscribe(kind) } {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            let _ = ready.send(Err(format!("WMI subscription failed: {}", e)));
+            return;
+        }
+    };
+    let _ = ready.send(Ok(()));
+
+    loop {
+        let mut objects = [None];
+        let mut returned = 0;
+        let result = unsafe { events.Next(WBEM_INFINITE, &mut objects, &mut returned) };
+        if let Err(e) = result.ok() {
+            log::error!("WMI process events stopped: {}", e);
+            return;
+        }
+        let Some(object) = objects[0].take() else {
+            continue;
+        };
+        let pid = unsafe {
+            if traced {
+                read_u32(&object, w!("ProcessID"))
+            } else {
+                target_instance(&object).and_then(|p| read_u32(&p, w!("ProcessId")))
+            }
+        };
+        if let Some(pid) = pid {
+            if tx.send(kind.event(pid)).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Returns the event enumerator and whether it carries trace events.
+unsafe fn subscribe(kind: Kind) -> windows::core::Result<(IEnumWbemClassObject, bool)> {
+    CoInitializeEx(None, COINIT_MULTITHREADED).ok()?;
+    let locator: IWbemLocator = CoCreateInstance(&WbemLocator, None, CLSCTX_INPROC_SERVER)?;
+    let services = locator.ConnectServer(
+        &BSTR::from("ROOT\\CIMV2"),
+        &BSTR::new(),
+        &BSTR::new(),
+        &BSTR::new(),
+        0,
+        &BSTR::new(),
+        None::<&IWbemContext>,
+    )?;
+    CoSetProxyBlanket(
+        &services,
+        RPC_C_AUTHN_WINNT,
+        RPC_C_AUTHZ_NONE,
+        PCWSTR::null(),
+        RPC_C_AUTHN_LEVEL_CALL,
+        RPC_C_IMP_LEVEL_IMPERSONATE,
+        None,
+        EOAC_NONE,
+    )?;
+
+    let query = |text: &str| {
+        services.ExecNotificationQuery(
+            &BSTR::from("WQL"),
+            &BSTR::from(text),
+            WBEM_FLAG_FORWARD_ONLY | WBEM_FLAG_RETURN_IMMEDIATELY,
+            None::<&IWbemContext>,
+        )
+    };
+    match query(kind.trace_query()) {
+        Ok(events) => Ok((events, true)),
+        Err(e) => {
+            log::info!(
+                "Process trace events unavailable ({}), falling back to instance events",
+                e.message()
+            );
+            Ok((query(kind.instance_query())?, false))
+        }
+    }
+}
+
+unsafe fn read_u32(object: &IWbemClassObject, name: PCWSTR) -> Option<u32> {
+    let mut value = VARIANT::default();
+    object.Get(name, 0, &mut value, None, None).ok()?;
+    u32::try_from(&value).ok()
+}
+
+unsafe fn target_instance(object: &IWbemClassObject) -> Option<IWbemClassObject> {
+    let mut value = VARIANT::default();
+    object
+        .Get(w!("TargetInstance"), 0, &mut value, None, None)
+        .ok()?;
+    windows::core::IUnknown::try_from(&value).ok()?.cast().ok()
+}
@@ -1,45 +1,465 @@
 use serde::{Deserialize, Serialize};
-use windows::{
-    core::PCWSTR,
-    Win32::Graphics::Gdi::{
-        ChangeDisplaySettingsW, EnumDisplaySettingsW, CDS_FULLSCREEN, DEVMODEW,
-        DISP_CHANGE_SUCCESSFUL, ENUM_CURRENT_SETTINGS, ENUM_DISPLAY_SETTINGS_MODE,
-    },
-};
+use std::fmt;
+use std::sync::OnceLock;
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg(any(target_os = "windows", target_os = "linux"))]
+mod edid;
+mod error;
+mod fake;
+#[cfg(target_os = "windows")]
+mod gdi;
+#[cfg(target_os = "linux")]
+mod kde_output;
+#[cfg(target_os = "linux")]
+mod wlr_output;
+#[cfg(target_os = "linux")]
+mod xrandr;
+
+pub use error::DisplayError;
+pub use fake::FakeBackend;
+#[cfg(target_os = "windows")]
+pub use gdi::GdiBackend;
+#[cfg(target_os = "linux")]
+pub use kde_output::KdeOutputBackend;
+#[cfg(target_os = "linux")]
+pub use wlr_output::WlrOutputBackend;
+#[cfg(target_os = "linux")]
+pub use xrandr::XrandrBackend;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
 pub struct Resolution {
     pub width: u32,
     pub height: u32,
     pub frequency: u32,
+    /// Color depth; `None` when the backend does not report it or any depth will do.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bits_per_pel: Option<u32>,
+    /// How the image is fitted to the panel; `None` leaves it to the driver.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scaling: Option<Scaling>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub interlaced: bool,
+    /// Orientation to switch to; `None` keeps the current one. When set,
+    /// `width`/`height` are in the panel's native orientation, so a 2560x1440
+    /// mode rotated 90° gives a 1440x2560 desktop.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<Rotation>,
+    /// Display this mode belongs to; `None` means the primary display.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<DisplayTarget>,
 }
 
-pub fn get_supported_resolutions() -> Vec<Resolution> {
-    let mut resolutions = Vec::new();
-    let mut dev_mode = DEVMODEW::default();
-    let mut mode_num = 0;
-
-    unsafe {
-        // ENUM_DISPLAY_SETTINGS_MODE is a newtype wrapper around u32 in newer windows-rs
-        while EnumDisplaySettingsW(
-            PCWSTR::null(),
-            ENUM_DISPLAY_SETTINGS_MODE(mode_num),
-            &mut dev_mode,
-        )
-        .as_bool()
-        {
-            let res = Resolution {
-                width: dev_mode.dmPelsWidth,
-                height: dev_mode.dmPelsHeight,
-                frequency: dev_mode.dmDisplayFrequency,
-            };
-
-            // Basic filtering
-            if !resolutions.contains(&res) {
-                resolutions.push(res);
+impl Resolution {
+    pub fn new(width: u32, height: u32, frequency: u32) -> Self {
+        Resolution {
+            width,
+            height,
+            frequency,
+            ..Default::default()
+        }
+    }
+
+    /// Whether both describe the same mode, ignoring which display they target.
+    /// Color depth and scaling only count when both sides specify them.
+    pub fn same_mode(&self, other: &Resolution) -> bool {
+        self.width == other.width
+            && self.height == other.height
+            && self.frequency == other.frequency
+            && self.same_format(other)
+    }
+
+    /// Whether color depth, scaling, rotation and scan type are compatible,
+    /// treating an unspecified depth, scaling or rotation as a wildcard.
+    pub fn same_format(&self, other: &Resolution) -> bool {
+        fn either<T: PartialEq>(a: &Option<T>, b: &Option<T>) -> bool {
+            a.is_none() || b.is_none() || a == b
+        }
+        either(&self.bits_per_pel, &other.bits_per_pel)
+            && either(&self.scaling, &other.scaling)
+            && either(&self.rotation, &other.rotation)
+            && self.interlaced == other.interlaced
+    }
+
+    /// Device name of the display this mode is tagged with.
+    pub fn device_name(&self) -> Option<&str> {
+        self.display.as_ref().map(|d| d.device_name.as_str())
+    }
+
+    /// Size of the desktop area this mode covers once rotated.
+    pub fn desktop_size(&self) -> (u32, u32) {
+        match self.rotation {
+            Some(rotation) if rotation.is_sideways() => (self.height, self.width),
+            _ => (self.width, self.height),
+        }
+    }
+}
+
+/// How a mode smaller than the panel's native resolution is shown.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum Scaling {
+    /// Fill the whole panel, distorting the aspect ratio if needed.
+    Stretched,
+    /// Native pixels in the middle of the panel, black borders around.
+    Centered,
+    /// Scale up as far as the aspect ratio allows, black bars on the sides.
+    AspectPreserving,
+}
+
+/// How long a mode change lasts.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Persistence {
+    /// Until the next change or sign-out; the stored desktop mode is untouched.
+    #[default]
+    Temporary,
+    /// Becomes the current user's stored desktop mode.
+    UpdateRegistry,
+    /// Becomes the stored desktop mode for every user of the machine.
+    Global,
+}
+
+/// What to do when a profile's exact mode is not offered by the display.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FallbackPolicy {
+    /// Fail unless width, height and refresh rate all match.
+    #[default]
+    Exact,
+    /// Same width and height at the highest refresh rate available.
+    SameResolution,
+    /// The mode whose aspect ratio is closest, then the closest size.
+    ClosestAspect,
+    /// The mode whose pixel count is closest, then the closest aspect ratio.
+    ClosestPixels,
+}
+
+/// Identity of a physical monitor, read from its EDID (or what the compositor
+/// decoded from it). Unlike device names it survives reboots and port changes.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorId {
+    /// PnP manufacturer code (`DEL`) or make reported by the compositor.
+    pub manufacturer: String,
+    /// Product code (`40F1`) or model name.
+    pub product: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial: Option<String>,
+}
+
+impl MonitorId {
+    /// Same model, and the same unit when both sides know the serial.
+    pub fn matches(&self, other: &MonitorId) -> bool {
+        self.manufacturer == other.manufacturer
+            && self.product == other.product
+            && match (&self.serial, &other.serial) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
             }
+    }
+}
+
+impl fmt::Display for MonitorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.manufacturer, self.product)?;
+        if let Some(serial) = &self.serial {
+            write!(f, " ({})", serial)?;
+        }
+        Ok(())
+    }
+}
+
+/// Which monitor a mode or profile is meant for.
+///
+/// Stored targets are resolved against the attached displays at apply time:
+/// by `monitor` first, then by `connector`, and only then by `device_name`,
+/// which the OS may hand to a different screen after a reboot.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayTarget {
+    /// OS-level name the display had when the target was saved (`\\.\DISPLAY1`, `HDMI-1`, ...).
+    pub device_name: String,
+    /// Port the monitor was plugged into; tells identical monitors apart.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connector: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub monitor: Option<MonitorId>,
+}
+
+impl DisplayTarget {
+    pub fn device(device_name: impl Into<String>) -> Self {
+        DisplayTarget {
+            device_name: device_name.into(),
+            connector: None,
+            monitor: None,
+        }
+    }
+
+    /// Whether the target only names a device and still needs its identity filled in.
+    pub fn is_bare(&self) -> bool {
+        self.connector.is_none() && self.monitor.is_none()
+    }
+}
+
+impl fmt::Display for DisplayTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.monitor, &self.connector) {
+            (Some(monitor), _) => write!(f, "{}", monitor),
+            (None, Some(connector)) => write!(f, "{}", connector),
+            (None, None) => write!(f, "{}", self.device_name),
+        }
+    }
+}
+
+/// A monitor currently attached to the desktop.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct DisplayInfo {
+    pub device_name: String,
+    /// Stable path of the port the monitor is on (the output name on Linux,
+    /// the monitor device interface path on Windows).
+    pub connector: String,
+    pub monitor: Option<MonitorId>,
+    /// Human readable monitor name.
+    pub name: String,
+    pub primary: bool,
+}
+
+impl DisplayInfo {
+    pub fn target(&self) -> DisplayTarget {
+        DisplayTarget {
+            device_name: self.device_name.clone(),
+            connector: Some(self.connector.clone()),
+            monitor: self.monitor.clone(),
+        }
+    }
+}
+
+/// Screen rotation, clockwise (RandR and Wayland count the other way round).
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum Rotation {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+}
+
+impl Rotation {
+    /// Whether the panel is in portrait, i.e. width and height trade places.
+    pub fn is_sideways(self) -> bool {
+        matches!(self, Rotation::Rotate90 | Rotation::Rotate270)
+    }
+}
+
+/// Complete state of one output within a topology snapshot.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputLayout {
+    pub display: DisplayTarget,
+    pub enabled: bool,
+    pub primary: bool,
+    /// Mode in the panel's native orientation; the last used mode when disabled.
+    pub mode: Resolution,
+    /// Top-left corner on the desktop.
+    pub x: i32,
+    pub y: i32,
+    #[serde(default)]
+    pub rotation: Rotation,
+}
+
+/// Every connected output (enabled or not) and how it is laid out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Topology {
+    pub outputs: Vec<OutputLayout>,
+}
+
+/// Platform layer used to enumerate and switch display modes.
+///
+/// Everything above this trait (Tauri commands, the process monitor) only
+/// talks to the active backend, so the switching logic does not depend on a
+/// particular OS API. Methods taking a `device` address the display with that
+/// `DisplayInfo::device_name`, or the primary display when it is `None`.
+pub trait DisplayBackend: Send + Sync {
+    /// Short identifier used in logs, e.g. "gdi".
+    fn name(&self) -> &'static str;
+
+    /// Monitors attached to the desktop.
+    fn displays(&self) -> Vec<DisplayInfo>;
+
+    /// All modes reported by the display, in driver order. May contain duplicates.
+    fn enumerate_modes(&self, device: Option<&str>) -> Vec<Resolution>;
+
+    /// The mode the display is currently running at.
+    fn current_mode(&self, device: Option<&str>) -> Option<Resolution>;
+
+    /// Switch the display to `res`. The mode must be one of `enumerate_modes`.
+    ///
+    /// Backends without a store of their own for desktop modes (XRandR,
+    /// wlroots) only apply the mode for the session whatever `persistence`
+    /// says; KWin stores every configuration it applies.
+    fn apply_mode(
+        &self,
+        device: Option<&str>,
+        res: &Resolution,
+        persistence: Persistence,
+    ) -> Result<(), DisplayError>;
+
+    /// Ask the driver whether `res` would be accepted without switching to it.
+    fn test_mode(&self, device: Option<&str>, res: &Resolution) -> Result<(), DisplayError>;
+
+    /// Snapshot of every connected output, including disabled ones.
+    fn topology(&self) -> Result<Topology, DisplayError>;
+
+    /// Applies all outputs of `topology` in one step, addressed by
+    /// `display.device_name`. Outputs it does not mention keep their state.
+    /// `persistence` is handled as in `apply_mode`.
+    fn apply_topology(
+        &self,
+        topology: &Topology,
+        persistence: Persistence,
+    ) -> Result<(), DisplayError>;
+}
+
+static BACKEND: OnceLock<Box<dyn DisplayBackend>> = OnceLock::new();
 
-            mode_num += 1;
+/// Returns the display backend for this session, selecting it on first use.
+///
+/// Setting `KORTEX_DISPLAY_BACKEND=fake` forces the in-memory backend, which is
+/// handy for working on the UI or the monitor logic without touching real displays.
+pub fn backend() -> &'static dyn DisplayBackend {
+    BACKEND
+        .get_or_init(|| {
+            let backend = select_backend();
+            log::info!("Using display backend: {}", backend.name());
+            backend
+        })
+        .as_ref()
+}
+
+fn select_backend() -> Box<dyn DisplayBackend> {
+    if std::env::var("KORTEX_DISPLAY_BACKEND").as_deref() == Ok("fake") {
+        return Box::new(FakeBackend::default());
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        Box::new(GdiBackend)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        // XWayland exposes RandR too, but mode changes there only affect the
+        // emulated X screen, so Wayland sessions must use a compositor protocol.
+        let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some_and(|v| !v.is_empty());
+        if wayland {
+            match WlrOutputBackend::connect() {
+                Ok(backend) => return Box::new(backend),
+                Err(e) => log::warn!("wlr-output-management backend unavailable: {}", e),
+            }
+            match KdeOutputBackend::connect() {
+                Ok(backend) => return Box::new(backend),
+                Err(e) => log::warn!("KDE output-management backend unavailable: {}", e),
+            }
+        } else {
+            match XrandrBackend::connect() {
+                Ok(backend) => return Box::new(backend),
+                Err(e) => log::warn!("XRandR backend unavailable: {}", e),
+            }
+        }
+        log::warn!("Falling back to fake display backend");
+        Box::new(FakeBackend::default())
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    {
+        log::warn!("No native display backend for this platform, falling back to fake");
+        Box::new(FakeBackend::default())
+    }
+}
+
+pub fn get_displays() -> Vec<DisplayInfo> {
+    backend().displays()
+}
+
+/// Finds the attached display a stored target refers to, or `None` when that
+/// monitor is not connected.
+pub fn find_display(target: &DisplayTarget) -> Option<DisplayInfo> {
+    find_matching(target, backend().displays(), DisplayInfo::target)
+}
+
+/// The attached display `display` refers to, the primary display for `None`.
+pub fn resolve_display(display: Option<&DisplayTarget>) -> Option<DisplayInfo> {
+    match display {
+        None => backend().displays().into_iter().find(|d| d.primary),
+        Some(target) => find_display(target),
+    }
+}
+
+/// Picks the item whose display matches `target`: by monitor identity, then by
+/// connector, then by device name.
+fn find_matching<T>(
+    target: &DisplayTarget,
+    items: Vec<T>,
+    target_of: impl Fn(&T) -> DisplayTarget,
+) -> Option<T> {
+    let mut candidates: Vec<T> = match &target.monitor {
+        Some(monitor) => items
+            .into_iter()
+            .filter(|item| target_of(item).monitor.is_some_and(|m| m.matches(monitor)))
+            .collect(),
+        None => items,
+    };
+    if target.monitor.is_some() && candidates.len() == 1 {
+        return candidates.pop();
+    }
+
+    // Several identical monitors (or no identity at all): narrow down by port.
+    if let Some(connector) = &target.connector {
+        return candidates
+            .into_iter()
+            .find(|item| target_of(item).connector.as_ref() == Some(connector));
+    }
+    candidates
+        .into_iter()
+        .find(|item| target_of(item).device_name == target.device_name)
+}
+
+/// Device name to pass to the backend for `display`, or a "display not
+/// connected" error when the stored monitor is missing.
+fn resolve_device(display: Option<&DisplayTarget>) -> Result<Option<String>, DisplayError> {
+    resolve_device_on(backend(), display)
+}
+
+fn resolve_device_on(
+    backend: &dyn DisplayBackend,
+    display: Option<&DisplayTarget>,
+) -> Result<Option<String>, DisplayError> {
+    match display {
+        None => Ok(None),
+        Some(target) => find_matching(target, backend.displays(), DisplayInfo::target)
+            .map(|d| Some(d.device_name))
+            .ok_or_else(|| DisplayError::device_not_found(target.to_string())),
+    }
+}
+
+pub fn get_supported_resolutions(display: Option<&DisplayTarget>) -> Vec<Resolution> {
+    let device = match resolve_device(display) {
+        Ok(device) => device,
+        Err(e) => {
+            log::warn!("{}", e);
+            return Vec::new();
+        }
+    };
+    let mut resolutions = Vec::new();
+
+    for res in backend().enumerate_modes(device.as_deref()) {
+        // Basic filtering
+        if !resolutions.contains(&res) {
+            resolutions.push(res);
         }
     }
 
@@ -48,61 +468,294 @@ pub fn get_supported_resolutions() -> Vec<Resolution> {
             .cmp(&a.width)
             .then(b.height.cmp(&a.height))
             .then(b.frequency.cmp(&a.frequency))
+            .then(b.bits_per_pel.cmp(&a.bits_per_pel))
+            .then(a.interlaced.cmp(&b.interlaced))
     });
 
     resolutions
 }
 
-pub fn get_current_resolution() -> Option<Resolution> {
-    let mut dev_mode = DEVMODEW::default();
-    unsafe {
-        if EnumDisplaySettingsW(PCWSTR::null(), ENUM_CURRENT_SETTINGS, &mut dev_mode).as_bool() {
-            Some(Resolution {
-                width: dev_mode.dmPelsWidth,
-                height: dev_mode.dmPelsHeight,
-                frequency: dev_mode.dmDisplayFrequency,
-            })
-        } else {
-            None
+/// Current mode of `display`, tagged with that display so it can be restored later.
+pub fn get_current_resolution(display: Option<&DisplayTarget>) -> Option<Resolution> {
+    let device = resolve_device(display).ok()?;
+    backend()
+        .current_mode(device.as_deref())
+        .map(|res| Resolution {
+            display: display.cloned(),
+            ..res
+        })
+}
+
+/// Picks the mode to apply for `res` on the display it targets: `res` itself
+/// when the display offers it, otherwise the best match under `policy`. The
+/// result is tagged with the same display as `res` and keeps any color depth
+/// or scaling `res` pins. Fallbacks never change those pinned attributes.
+pub fn resolve_mode(res: &Resolution, policy: FallbackPolicy) -> Result<Resolution, DisplayError> {
+    resolve_mode_on(backend(), res, policy)
+}
+
+fn resolve_mode_on(
+    backend: &dyn DisplayBackend,
+    res: &Resolution,
+    policy: FallbackPolicy,
+) -> Result<Resolution, DisplayError> {
+    let device = resolve_device_on(backend, res.display.as_ref())?;
+    let modes: Vec<Resolution> = backend
+        .enumerate_modes(device.as_deref())
+        .into_iter()
+        .filter(|m| m.same_format(res))
+        .collect();
+    let tagged = |mode: &Resolution| Resolution {
+        bits_per_pel: res.bits_per_pel.or(mode.bits_per_pel),
+        scaling: res.scaling.or(mode.scaling),
+        rotation: res.rotation.or(mode.rotation),
+        display: res.display.clone(),
+        ..mode.clone()
+    };
+
+    if let Some(exact) = modes.iter().find(|m| m.same_mode(res)) {
+        return Ok(tagged(exact));
+    }
+
+    let aspect = |m: &Resolution| m.width as f64 / m.height.max(1) as f64;
+    let aspect_diff = |m: &Resolution| (aspect(m) - aspect(res)).abs();
+    let pixel_diff = |m: &Resolution| {
+        (m.width as i64 * m.height as i64 - res.width as i64 * res.height as i64).abs()
+    };
+    let refresh_diff = |m: &Resolution| (m.frequency as i64 - res.frequency as i64).abs();
+
+    let best = match policy {
+        FallbackPolicy::Exact => None,
+        FallbackPolicy::SameResolution => modes
+            .iter()
+            .filter(|m| m.width == res.width && m.height == res.height)
+            .max_by_key(|m| m.frequency),
+        FallbackPolicy::ClosestAspect => modes.iter().min_by(|a, b| {
+            aspect_diff(a)
+                .total_cmp(&aspect_diff(b))
+                .then(pixel_diff(a).cmp(&pixel_diff(b)))
+                .then(refresh_diff(a).cmp(&refresh_diff(b)))
+                .then(b.frequency.cmp(&a.frequency))
+        }),
+        FallbackPolicy::ClosestPixels => modes.iter().min_by(|a, b| {
+            pixel_diff(a)
+                .cmp(&pixel_diff(b))
+                .then(aspect_diff(a).total_cmp(&aspect_diff(b)))
+                .then(refresh_diff(a).cmp(&refresh_diff(b)))
+                .then(b.frequency.cmp(&a.frequency))
+        }),
+    };
+
+    match best {
+        Some(mode) => {
+            log::info!(
+                "{}x{}@{} is not available, falling back to {}x{}@{} ({:?})",
+                res.width,
+                res.height,
+                res.frequency,
+                mode.width,
+                mode.height,
+                mode.frequency,
+                policy
+            );
+            Ok(tagged(mode))
         }
+        None => Err(DisplayError::mode_not_supported(res, device.as_deref())),
     }
 }
 
-pub fn change_resolution(res: Resolution) -> Result<(), String> {
-    let mut dev_mode = DEVMODEW::default();
-    let mut found_mode = None;
-    let mut mode_num = 0;
+/// Applies `res` to the display it targets (the primary display if it has none).
+/// Fails with [`DisplayError::DeviceNotFound`] rather than falling back to another screen.
+pub fn change_resolution(res: Resolution, persistence: Persistence) -> Result<(), DisplayError> {
+    let device = resolve_device(res.display.as_ref())?;
+    backend().apply_mode(device.as_deref(), &res, persistence)
+}
+
+/// Asks the driver whether `res` would be accepted on the display it targets,
+/// without switching to it (CDS_TEST on Windows, a compositor test or mode
+/// check elsewhere).
+pub fn test_resolution(res: &Resolution) -> Result<(), DisplayError> {
+    let device = resolve_device(res.display.as_ref())?;
+    backend().test_mode(device.as_deref(), res)
+}
 
-    unsafe {
-        while EnumDisplaySettingsW(
-            PCWSTR::null(),
-            ENUM_DISPLAY_SETTINGS_MODE(mode_num),
-            &mut dev_mode,
-        )
-        .as_bool()
-        {
-            if dev_mode.dmPelsWidth == res.width
-                && dev_mode.dmPelsHeight == res.height
-                && dev_mode.dmDisplayFrequency == res.frequency
-            {
-                found_mode = Some(dev_mode);
-                break;
+/// Captures the current layout of every connected output.
+pub fn get_topology() -> Result<Topology, DisplayError> {
+    backend().topology()
+}
+
+/// Applies a topology snapshot atomically.
+///
+/// Stored targets are resolved against the connected outputs first. A missing
+/// output that the snapshot wants enabled fails the whole change with
+/// [`DisplayError::DeviceNotFound`]; a missing output it wants disabled is
+/// already in that state.
+pub fn apply_topology(topology: &Topology, persistence: Persistence) -> Result<(), DisplayError> {
+    let current = backend().topology()?;
+    let mut outputs = Vec::new();
+
+    for layout in &topology.outputs {
+        let live = find_matching(&layout.display, current.outputs.iter().collect(), |o| {
+            o.display.clone()
+        });
+        match live {
+            Some(live) => outputs.push(OutputLayout {
+                display: live.display.clone(),
+                ..layout.clone()
+            }),
+            None if layout.enabled => {
+                return Err(DisplayError::device_not_found(layout.display.to_string()))
             }
-            mode_num += 1;
+            None => log::info!("Skipping disconnected display {}", layout.display),
         }
+    }
 
-        if let Some(mut target_mode) = found_mode {
-            let result = ChangeDisplaySettingsW(Some(&mut target_mode), CDS_FULLSCREEN);
-            if result == DISP_CHANGE_SUCCESSFUL {
-                Ok(())
-            } else {
-                Err(format!(
-                    "ChangeDisplaySettings failed with code: {:?}",
-                    result
-                ))
-            }
-        } else {
-            Err("Resolution not found in supported modes".to_string())
+    backend().apply_topology(&Topology { outputs }, persistence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(device_name: &str, connector: &str, product: Option<&str>) -> DisplayInfo {
+        DisplayInfo {
+            device_name: device_name.to_string(),
+            connector: connector.to_string(),
+            monitor: product.map(|product| MonitorId {
+                manufacturer: "DEL".to_string(),
+                product: product.to_string(),
+                serial: None,
+            }),
+            name: device_name.to_string(),
+            primary: false,
         }
     }
+
+    fn target(device_name: &str, connector: Option<&str>, product: Option<&str>) -> DisplayTarget {
+        DisplayTarget {
+            connector: connector.map(str::to_string),
+            ..display(device_name, "", product).target()
+        }
+    }
+
+    fn find(target: &DisplayTarget, displays: &[DisplayInfo]) -> Option<String> {
+        find_matching(target, displays.to_vec(), DisplayInfo::target).map(|d| d.device_name)
+    }
+
+    fn resolve(res: Resolution, policy: FallbackPolicy) -> Result<Resolution, DisplayError> {
+        resolve_mode_on(&FakeBackend::default(), &res, policy)
+    }
+
+    #[test]
+    fn find_matching_prefers_monitor_identity_over_device_name() {
+        // The OS swapped the device names of the two monitors after a reboot.
+        let displays = [
+            display("DISPLAY1", "port-b", Some("B000")),
+            display("DISPLAY2", "port-a", Some("A000")),
+        ];
+        let stored = target("DISPLAY1", Some("port-a"), Some("A000"));
+        assert_eq!(find(&stored, &displays).as_deref(), Some("DISPLAY2"));
+    }
+
+    #[test]
+    fn find_matching_tells_identical_monitors_apart_by_connector() {
+        let displays = [
+            display("DISPLAY1", "port-a", Some("A000")),
+            display("DISPLAY2", "port-b", Some("A000")),
+        ];
+        let stored = target("DISPLAY1", Some("port-b"), Some("A000"));
+        assert_eq!(find(&stored, &displays).as_deref(), Some("DISPLAY2"));
+    }
+
+    #[test]
+    fn find_matching_falls_back_to_device_name() {
+        let displays = [
+            display("DISPLAY1", "port-a", None),
+            display("DISPLAY2", "port-b", None),
+        ];
+        let stored = DisplayTarget::device("DISPLAY2");
+        assert_eq!(find(&stored, &displays).as_deref(), Some("DISPLAY2"));
+    }
+
+    #[test]
+    fn find_matching_ignores_device_name_of_a_missing_monitor() {
+        let displays = [display("DISPLAY1", "port-a", Some("B000"))];
+        let stored = target("DISPLAY1", Some("port-a"), Some("A000"));
+        assert_eq!(find(&stored, &displays), None);
+    }
+
+    #[test]
+    fn resolve_mode_keeps_an_offered_mode() {
+        let mode = resolve(Resolution::new(1920, 1080, 144), FallbackPolicy::Exact).unwrap();
+        assert!(mode.same_mode(&Resolution::new(1920, 1080, 144)));
+        assert_eq!(mode.bits_per_pel, Some(32));
+    }
+
+    #[test]
+    fn resolve_mode_exact_fails_without_the_mode() {
+        let result = resolve(Resolution::new(1920, 1080, 120), FallbackPolicy::Exact);
+        assert!(matches!(result, Err(DisplayError::ModeNotSupported { .. })));
+    }
+
+    #[test]
+    fn resolve_mode_same_resolution_takes_the_highest_refresh_rate() {
+        let mode = resolve(
+            Resolution::new(1920, 1080, 120),
+            FallbackPolicy::SameResolution,
+        )
+        .unwrap();
+        assert!(mode.same_mode(&Resolution::new(1920, 1080, 144)));
+    }
+
+    #[test]
+    fn resolve_mode_same_resolution_keeps_a_pinned_depth() {
+        let res = Resolution {
+            bits_per_pel: Some(16),
+            ..Resolution::new(1920, 1080, 144)
+        };
+        let mode = resolve(res, FallbackPolicy::SameResolution).unwrap();
+        assert_eq!((mode.frequency, mode.bits_per_pel), (60, Some(16)));
+    }
+
+    #[test]
+    fn resolve_mode_closest_aspect_keeps_the_aspect_ratio() {
+        let mode = resolve(
+            Resolution::new(1600, 900, 60),
+            FallbackPolicy::ClosestAspect,
+        )
+        .unwrap();
+        assert_eq!((mode.width, mode.height), (1280, 720));
+    }
+
+    #[test]
+    fn resolve_mode_closest_pixels_keeps_the_pixel_count() {
+        let mode = resolve(
+            Resolution::new(1280, 960, 60),
+            FallbackPolicy::ClosestPixels,
+        )
+        .unwrap();
+        assert_eq!((mode.width, mode.height), (1280, 1024));
+    }
+
+    #[test]
+    fn resolve_mode_uses_the_modes_of_the_target_display() {
+        let side = FakeBackend::default().displays()[1].target();
+        let res = Resolution {
+            display: Some(side.clone()),
+            ..Resolution::new(2560, 1440, 144)
+        };
+        let mode = resolve(res, FallbackPolicy::ClosestPixels).unwrap();
+        assert!(mode.same_mode(&Resolution::new(1920, 1080, 60)));
+        assert_eq!(mode.display, Some(side));
+    }
+
+    #[test]
+    fn resolve_mode_fails_for_a_missing_display() {
+        let res = Resolution {
+            display: Some(target("FAKE-3", Some("fake-fake-3"), Some("0003"))),
+            ..Resolution::new(1920, 1080, 60)
+        };
+        let result = resolve(res, FallbackPolicy::ClosestPixels);
+        assert!(matches!(result, Err(DisplayError::DeviceNotFound { .. })));
+    }
 }
@@ -0,0 +1,116 @@
+//! Minimal EDID parsing for telling monitors apart.
+
+use super::MonitorId;
+
+const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+
+/// Manufacturer, product code and serial from the EDID base block.
+///
+/// The product code is formatted in hex so the id reads like the PnP hardware
+/// id Windows uses (`DEL` + `40F1`). The serial prefers the serial number
+/// descriptor (tag 0xFF) over the numeric field, which many panels leave at 0.
+pub fn monitor_id(edid: &[u8]) -> Option<MonitorId> {
+    if edid.len() < 16 || edid[..8] != HEADER {
+        return None;
+    }
+
+    // Manufacturer: big-endian, three 5-bit letters where 1 = 'A'.
+    let raw = u16::from_be_bytes([edid[8], edid[9]]);
+    let letter = |shift: u16| (b'A' - 1 + ((raw >> shift) & 0x1F) as u8) as char;
+    let manufacturer: String = [letter(10), letter(5), letter(0)].iter().collect();
+
+    let product = u16::from_le_bytes([edid[10], edid[11]]);
+    let serial_number = u32::from_le_bytes([edid[12], edid[13], edid[14], edid[15]]);
+    let serial = descriptor_text(edid, 0xFF)
+        .or_else(|| (serial_number != 0).then(|| serial_number.to_string()));
+
+    Some(MonitorId {
+        manufacturer,
+        product: format!("{:04X}", product),
+        serial,
+    })
+}
+
+/// Monitor name from the display descriptor blocks (tag 0xFC), if present.
+pub fn monitor_name(edid: &[u8]) -> Option<String> {
+    descriptor_text(edid, 0xFC)
+}
+
+fn descriptor_text(edid: &[u8], tag: u8) -> Option<String> {
+    // Four 18-byte descriptors start at offset 54 of the base block.
+    (0..4).find_map(|i| {
+        let start = 54 + i * 18;
+        let block = edid.get(start..start + 18)?;
+        if block[..3] != [0, 0, 0] || block[3] != tag {
+            return None;
+        }
+        let text: String = block[5..]
+            .iter()
+            .take_while(|b| **b != b'\n')
+            .map(|b| *b as char)
+            .collect();
+        let text = text.trim().to_string();
+        (!text.is_empty()).then_some(text)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Base block for a `DEL` monitor with product code 0x40F1.
+    fn edid(serial_number: u32) -> Vec<u8> {
+        let mut edid = vec![0; 128];
+        edid[..8].copy_from_slice(&HEADER);
+        // D = 4, E = 5, L = 12
+        edid[8..10].copy_from_slice(&((4u16 << 10) | (5 << 5) | 12).to_be_bytes());
+        edid[10..12].copy_from_slice(&0x40F1u16.to_le_bytes());
+        edid[12..16].copy_from_slice(&serial_number.to_le_bytes());
+        edid
+    }
+
+    fn with_descriptor(mut edid: Vec<u8>, slot: usize, tag: u8, text: &str) -> Vec<u8> {
+        let start = 54 + slot * 18;
+        edid[start + 3] = tag;
+        let mut payload = text.as_bytes().to_vec();
+        payload.push(b'\n');
+        payload.resize(13, b' ');
+        edid[start + 5..start + 18].copy_from_slice(&payload);
+        edid
+    }
+
+    #[test]
+    fn monitor_id_reads_manufacturer_product_and_serial() {
+        let id = monitor_id(&edid(12345)).unwrap();
+        assert_eq!(id.manufacturer, "DEL");
+        assert_eq!(id.product, "40F1");
+        assert_eq!(id.serial.as_deref(), Some("12345"));
+    }
+
+    #[test]
+    fn monitor_id_prefers_the_serial_descriptor() {
+        let edid = with_descriptor(edid(12345), 1, 0xFF, "CN0ABC123");
+        let id = monitor_id(&edid).unwrap();
+        assert_eq!(id.serial.as_deref(), Some("CN0ABC123"));
+    }
+
+    #[test]
+    fn monitor_id_leaves_out_a_zero_serial() {
+        assert_eq!(monitor_id(&edid(0)).unwrap().serial, None);
+    }
+
+    #[test]
+    fn monitor_id_rejects_a_bad_header() {
+        let mut edid = edid(1);
+        edid[0] = 0xFF;
+        assert_eq!(monitor_id(&edid), None);
+        assert_eq!(monitor_id(&HEADER), None);
+    }
+
+    #[test]
+    fn monitor_name_reads_the_name_descriptor() {
+        let named = with_descriptor(edid(0), 2, 0xFC, "DELL U2720Q");
+        assert_eq!(monitor_name(&named).as_deref(), Some("DELL U2720Q"));
+        assert_eq!(monitor_name(&edid(0)), None);
+    }
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use super::Resolution;
+#[cfg(target_os = "linux")]
+use super::{Rotation, Scaling};
+
+/// Why a display operation failed. Serialized as `{ "kind": "modeNotSupported", ... }`
+/// so the frontend can tell the user what to do about it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DisplayError {
+    /// The display does not offer the mode, or the driver refused it in a dry run.
+    ModeNotSupported {
+        mode: String,
+        device: Option<String>,
+    },
+    /// The driver accepted the mode but it only takes effect after a reboot.
+    RestartRequired,
+    /// The backend was called with an invalid combination of flags.
+    BadFlags,
+    /// The request itself is invalid, e.g. a layout larger than the screen allows.
+    BadParams { message: String },
+    /// The display is not connected (or no longer has that name).
+    DeviceNotFound { device: String },
+    /// The platform API failed for another reason; trying again may help.
+    BackendFailure { message: String },
+}
+
+impl DisplayError {
+    pub fn mode_not_supported(res: &Resolution, device: Option<&str>) -> Self {
+        DisplayError::ModeNotSupported {
+            mode: format!("{}x{}@{}Hz", res.width, res.height, res.frequency),
+            device: device.map(str::to_string),
+        }
+    }
+
+    /// Only the Linux backends lack a scaling setting; GDI always has one.
+    #[cfg(target_os = "linux")]
+    pub fn scaling_not_supported(device: &str, scaling: Scaling) -> Self {
+        DisplayError::BadParams {
+            message: format!("{} does not support {:?} scaling", device, scaling),
+        }
+    }
+
+    /// GDI rotates every display; XRandR CRTCs may not.
+    #[cfg(target_os = "linux")]
+    pub fn rotation_not_supported(device: &str, rotation: Rotation) -> Self {
+        DisplayError::BadParams {
+            message: format!("{} does not support {:?}", device, rotation),
+        }
+    }
+
+    pub fn device_not_found(device: impl Into<String>) -> Self {
+        DisplayError::DeviceNotFound {
+            device: device.into(),
+        }
+    }
+
+    /// Whether retrying the same change could succeed. Everything except a
+    /// backend hiccup is a definite answer from the driver or compositor.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, DisplayError::BackendFailure { .. })
+    }
+}
+
+impl fmt::Display for DisplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayError::ModeNotSupported {
+                mode,
+                device: Some(device),
+            } => write!(f, "{} is not supported by {}", mode, device),
+            DisplayError::ModeNotSupported { mode, device: None } => {
+                write!(f, "{} is not supported by the display", mode)
+            }
+            DisplayError::RestartRequired => {
+                write!(f, "The computer must be restarted for the change to apply")
+            }
+            DisplayError::BadFlags => write!(f, "Invalid display change flags"),
+            DisplayError::BadParams { message } => write!(f, "Invalid parameters: {}", message),
+            DisplayError::DeviceNotFound { device } => {
+                write!(f, "Display not connected: {}", device)
+            }
+            DisplayError::BackendFailure { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for DisplayError {}
+
+/// Errors from the underlying platform calls (X11, Wayland, Win32) are plain
+/// strings; they all count as backend failures.
+impl From<String> for DisplayError {
+    fn from(message: String) -> Self {
+        DisplayError::BackendFailure { message }
+    }
+}
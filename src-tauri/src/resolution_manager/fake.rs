@@ -0,0 +1,252 @@
+use std::sync::Mutex;
+
+use super::{
+    DisplayBackend, DisplayError, DisplayInfo, MonitorId, OutputLayout, Persistence, Resolution,
+    Rotation, Topology,
+};
+
+/// In-memory displays used when no real backend is available (or forced via
+/// `KORTEX_DISPLAY_BACKEND=fake`). Mode changes only update the stored state.
+pub struct FakeBackend {
+    displays: Vec<FakeDisplay>,
+}
+
+pub struct FakeDisplay {
+    pub info: DisplayInfo,
+    pub modes: Vec<Resolution>,
+    state: Mutex<FakeState>,
+}
+
+/// The mutable part of a fake display; `info.primary` is only the initial value.
+struct FakeState {
+    mode: Resolution,
+    enabled: bool,
+    primary: bool,
+    position: (i32, i32),
+    rotation: Rotation,
+}
+
+impl FakeDisplay {
+    pub fn new(info: DisplayInfo, modes: Vec<Resolution>, current: Resolution) -> Self {
+        let primary = info.primary;
+        FakeDisplay {
+            info,
+            modes,
+            state: Mutex::new(FakeState {
+                mode: current,
+                enabled: true,
+                primary,
+                position: (0, 0),
+                rotation: Rotation::Normal,
+            }),
+        }
+    }
+
+    fn at(self, x: i32, y: i32) -> Self {
+        if let Ok(mut state) = self.state.lock() {
+            state.position = (x, y);
+        }
+        self
+    }
+
+    /// The listed mode matching `res`, with the scaling `res` asks for.
+    fn find_mode(&self, res: &Resolution) -> Result<Resolution, DisplayError> {
+        match self.modes.iter().find(|m| m.same_mode(res)) {
+            Some(mode) => Ok(Resolution {
+                scaling: res.scaling,
+                ..mode.clone()
+            }),
+            None => Err(DisplayError::mode_not_supported(
+                res,
+                Some(&self.info.device_name),
+            )),
+        }
+    }
+}
+
+impl FakeBackend {
+    pub fn new(displays: Vec<FakeDisplay>) -> Self {
+        FakeBackend { displays }
+    }
+
+    fn display(&self, device: Option<&str>) -> Result<&FakeDisplay, DisplayError> {
+        let enabled = |d: &&FakeDisplay| d.state.lock().is_ok_and(|s| s.enabled);
+        let display = match device {
+            Some(name) => self
+                .displays
+                .iter()
+                .filter(enabled)
+                .find(|d| d.info.device_name == name),
+            None => self
+                .displays
+                .iter()
+                .filter(enabled)
+                .find(|d| d.state.lock().is_ok_and(|s| s.primary)),
+        };
+        display.ok_or_else(|| DisplayError::device_not_found(device.unwrap_or("primary")))
+    }
+}
+
+impl Default for FakeBackend {
+    fn default() -> Self {
+        let info = |device_name: &str, product: &str, name: &str, primary| DisplayInfo {
+            device_name: device_name.to_string(),
+            connector: format!("fake-{}", device_name.to_lowercase()),
+            monitor: Some(MonitorId {
+                manufacturer: "FAK".to_string(),
+                product: product.to_string(),
+                serial: None,
+            }),
+            name: name.to_string(),
+            primary,
+        };
+
+        let mode = |width, height, frequency, bits_per_pel| Resolution {
+            bits_per_pel: Some(bits_per_pel),
+            ..Resolution::new(width, height, frequency)
+        };
+        let main_modes = vec![
+            mode(2560, 1440, 144, 32),
+            mode(2560, 1440, 60, 32),
+            mode(1920, 1080, 144, 32),
+            mode(1920, 1080, 60, 32),
+            mode(1920, 1080, 60, 16),
+            Resolution {
+                interlaced: true,
+                ..mode(1920, 1080, 60, 32)
+            },
+            mode(1280, 1024, 60, 32),
+            mode(1280, 720, 60, 32),
+        ];
+        let side_modes = vec![
+            mode(1920, 1080, 60, 32),
+            mode(1680, 1050, 60, 32),
+            mode(1280, 720, 60, 32),
+        ];
+
+        FakeBackend::new(vec![
+            FakeDisplay::new(
+                info("FAKE-1", "0001", "Fake Main Monitor", true),
+                main_modes.clone(),
+                main_modes[0].clone(),
+            ),
+            FakeDisplay::new(
+                info("FAKE-2", "0002", "Fake Side Monitor", false),
+                side_modes.clone(),
+                side_modes[0].clone(),
+            )
+            .at(2560, 0),
+        ])
+    }
+}
+
+impl DisplayBackend for FakeBackend {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn displays(&self) -> Vec<DisplayInfo> {
+        self.displays
+            .iter()
+            .filter_map(|d| {
+                let state = d.state.lock().ok()?;
+                state.enabled.then(|| DisplayInfo {
+                    primary: state.primary,
+                    ..d.info.clone()
+                })
+            })
+            .collect()
+    }
+
+    fn enumerate_modes(&self, device: Option<&str>) -> Vec<Resolution> {
+        self.display(device)
+            .map(|d| d.modes.clone())
+            .unwrap_or_default()
+    }
+
+    fn current_mode(&self, device: Option<&str>) -> Option<Resolution> {
+        let display = self.display(device).ok()?;
+        display.state.lock().ok().map(|state| Resolution {
+            rotation: Some(state.rotation),
+            ..state.mode.clone()
+        })
+    }
+
+    fn apply_mode(
+        &self,
+        device: Option<&str>,
+        res: &Resolution,
+        _persistence: Persistence,
+    ) -> Result<(), DisplayError> {
+        let display = self.display(device)?;
+        let mode = display.find_mode(res)?;
+        let mut state = display.state.lock().map_err(|e| e.to_string())?;
+        state.mode = mode;
+        if let Some(rotation) = res.rotation {
+            state.rotation = rotation;
+        }
+        Ok(())
+    }
+
+    fn test_mode(&self, device: Option<&str>, res: &Resolution) -> Result<(), DisplayError> {
+        self.display(device)?.find_mode(res).map(|_| ())
+    }
+
+    fn topology(&self) -> Result<Topology, DisplayError> {
+        let mut outputs = Vec::new();
+        for display in &self.displays {
+            let state = display.state.lock().map_err(|e| e.to_string())?;
+            outputs.push(OutputLayout {
+                display: display.info.target(),
+                enabled: state.enabled,
+                primary: state.primary,
+                mode: state.mode.clone(),
+                x: state.position.0,
+                y: state.position.1,
+                rotation: state.rotation,
+            });
+        }
+        Ok(Topology { outputs })
+    }
+
+    fn apply_topology(
+        &self,
+        topology: &Topology,
+        _persistence: Persistence,
+    ) -> Result<(), DisplayError> {
+        // Validate everything up front so a bad output leaves nothing half-applied.
+        let mut changes = Vec::new();
+        for layout in &topology.outputs {
+            let display = self
+                .displays
+                .iter()
+                .find(|d| d.info.device_name == layout.display.device_name)
+                .ok_or_else(|| DisplayError::device_not_found(&layout.display.device_name))?;
+            let mode = if layout.enabled {
+                Some(display.find_mode(&layout.mode)?)
+            } else {
+                None
+            };
+            changes.push((display, layout, mode));
+        }
+
+        let sets_primary = changes.iter().any(|(_, l, _)| l.enabled && l.primary);
+        for display in &self.displays {
+            let mut state = display.state.lock().map_err(|e| e.to_string())?;
+            if let Some((_, layout, mode)) =
+                changes.iter().find(|(d, _, _)| std::ptr::eq(*d, display))
+            {
+                state.enabled = layout.enabled;
+                state.primary = layout.enabled && layout.primary;
+                state.position = (layout.x, layout.y);
+                state.rotation = layout.rotation;
+                if let Some(mode) = mode {
+                    state.mode = mode.clone();
+                }
+            } else if sets_primary {
+                state.primary = false;
+            }
+        }
+        Ok(())
+    }
+}
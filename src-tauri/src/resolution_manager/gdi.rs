@@ -0,0 +1,594 @@
+use windows::{
+    core::PCWSTR,
+    Win32::Foundation::ERROR_SUCCESS,
+    Win32::Graphics::Gdi::{
+        ChangeDisplaySettingsExW, EnumDisplayDevicesW, EnumDisplaySettingsW, CDS_FULLSCREEN,
+        CDS_GLOBAL, CDS_NORESET, CDS_SET_PRIMARY, CDS_TEST, CDS_TYPE, CDS_UPDATEREGISTRY, DEVMODEW,
+        DEVMODE_DISPLAY_FIXED_OUTPUT, DEVMODE_DISPLAY_ORIENTATION, DISPLAY_DEVICEW,
+        DISPLAY_DEVICE_ATTACHED_TO_DESKTOP, DISPLAY_DEVICE_PRIMARY_DEVICE, DISP_CHANGE,
+        DISP_CHANGE_BADDUALVIEW, DISP_CHANGE_BADFLAGS, DISP_CHANGE_BADMODE, DISP_CHANGE_BADPARAM,
+        DISP_CHANGE_RESTART, DISP_CHANGE_SUCCESSFUL, DMDFO_CENTER, DMDFO_DEFAULT, DMDFO_STRETCH,
+        DMDO_180, DMDO_270, DMDO_90, DMDO_DEFAULT, DM_BITSPERPEL, DM_DISPLAYFIXEDOUTPUT,
+        DM_DISPLAYFLAGS, DM_DISPLAYFREQUENCY, DM_DISPLAYORIENTATION, DM_INTERLACED, DM_PELSHEIGHT,
+        DM_PELSWIDTH, DM_POSITION, ENUM_CURRENT_SETTINGS, ENUM_DISPLAY_SETTINGS_MODE,
+        ENUM_REGISTRY_SETTINGS,
+    },
+    Win32::System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_BINARY},
+    Win32::UI::WindowsAndMessaging::EDD_GET_DEVICE_INTERFACE_NAME,
+};
+
+use super::{
+    edid, DisplayBackend, DisplayError, DisplayInfo, OutputLayout, Persistence, Resolution,
+    Rotation, Scaling, Topology,
+};
+
+/// Win32 GDI backend (`EnumDisplaySettingsW` / `ChangeDisplaySettingsExW`).
+pub struct GdiBackend;
+
+/// Null-terminated device name for the GDI calls; `None` addresses the primary display.
+struct Device(Option<Vec<u16>>);
+
+impl Device {
+    fn new(name: Option<&str>) -> Self {
+        Device(name.map(to_wide))
+    }
+
+    fn as_pcwstr(&self) -> PCWSTR {
+        match &self.0 {
+            Some(name) => PCWSTR(name.as_ptr()),
+            None => PCWSTR::null(),
+        }
+    }
+}
+
+fn to_wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+fn from_wide(buf: &[u16]) -> String {
+    let len = buf.iter().position(|c| *c == 0).unwrap_or(buf.len());
+    String::from_utf16_lossy(&buf[..len])
+}
+
+fn to_resolution(dev_mode: &DEVMODEW) -> Resolution {
+    let (fixed_output, display_flags) = unsafe {
+        (
+            dev_mode.Anonymous1.Anonymous2.dmDisplayFixedOutput,
+            dev_mode.Anonymous2.dmDisplayFlags,
+        )
+    };
+    Resolution {
+        bits_per_pel: (dev_mode.dmBitsPerPel != 0).then_some(dev_mode.dmBitsPerPel),
+        scaling: from_fixed_output(fixed_output),
+        interlaced: display_flags & DM_INTERLACED.0 != 0,
+        ..Resolution::new(
+            dev_mode.dmPelsWidth,
+            dev_mode.dmPelsHeight,
+            dev_mode.dmDisplayFrequency,
+        )
+    }
+}
+
+/// GDI has no aspect-preserving flag; `DMDFO_DEFAULT` hands the choice to the
+/// driver, which keeps the aspect ratio unless told otherwise in its own panel.
+fn to_fixed_output(scaling: Scaling) -> DEVMODE_DISPLAY_FIXED_OUTPUT {
+    match scaling {
+        Scaling::Stretched => DMDFO_STRETCH,
+        Scaling::Centered => DMDFO_CENTER,
+        Scaling::AspectPreserving => DMDFO_DEFAULT,
+    }
+}
+
+fn from_fixed_output(fixed_output: DEVMODE_DISPLAY_FIXED_OUTPUT) -> Option<Scaling> {
+    match fixed_output {
+        DMDFO_STRETCH => Some(Scaling::Stretched),
+        DMDFO_CENTER => Some(Scaling::Centered),
+        _ => None,
+    }
+}
+
+/// Pins the color depth, scaling and scan type `res` asks for on a DEVMODE
+/// picked from the driver's list.
+fn apply_format(dev_mode: &mut DEVMODEW, res: &Resolution) {
+    if let Some(bits_per_pel) = res.bits_per_pel {
+        dev_mode.dmBitsPerPel = bits_per_pel;
+        dev_mode.dmFields |= DM_BITSPERPEL;
+    }
+    if let Some(scaling) = res.scaling {
+        dev_mode.Anonymous1.Anonymous2.dmDisplayFixedOutput = to_fixed_output(scaling);
+        dev_mode.dmFields |= DM_DISPLAYFIXEDOUTPUT;
+    }
+    dev_mode.Anonymous2.dmDisplayFlags = if res.interlaced { DM_INTERLACED.0 } else { 0 };
+    dev_mode.dmFields |= DM_DISPLAYFLAGS;
+}
+
+/// Reads the EDID the monitor driver cached in the registry.
+///
+/// `interface` is the monitor's device interface path, e.g.
+/// `\\?\DISPLAY#DEL40F1#5&1a2b3c&0&UID4352#{e6f07b5f-...}`; its first three
+/// segments are the device instance under `HKLM\...\Enum`.
+fn read_edid(interface: &str) -> Option<Vec<u8>> {
+    let parts: Vec<&str> = interface
+        .trim_start_matches(r"\\?\")
+        .split('#')
+        .take(3)
+        .collect();
+    if parts.len() != 3 {
+        return None;
+    }
+    let key = to_wide(&format!(
+        r"SYSTEM\CurrentControlSet\Enum\{}\Device Parameters",
+        parts.join(r"\")
+    ));
+    let value = to_wide("EDID");
+
+    let mut buf = vec![0u8; 512];
+    let mut size = buf.len() as u32;
+    let status = unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(key.as_ptr()),
+            PCWSTR(value.as_ptr()),
+            RRF_RT_REG_BINARY,
+            None,
+            Some(buf.as_mut_ptr().cast()),
+            Some(&mut size),
+        )
+    };
+    if status != ERROR_SUCCESS {
+        return None;
+    }
+    buf.truncate(size as usize);
+    Some(buf)
+}
+
+/// An adapter output with a monitor behind it, attached to the desktop or not.
+struct Output {
+    info: DisplayInfo,
+    attached: bool,
+}
+
+/// Registry settings of an output, kept to undo staged changes.
+struct StoredOutput {
+    device: Device,
+    dev_mode: DEVMODEW,
+    primary: bool,
+}
+
+/// `dmDisplayOrientation` counts clockwise, like `Rotation`.
+fn to_orientation(rotation: Rotation) -> DEVMODE_DISPLAY_ORIENTATION {
+    match rotation {
+        Rotation::Normal => DMDO_DEFAULT,
+        Rotation::Rotate90 => DMDO_90,
+        Rotation::Rotate180 => DMDO_180,
+        Rotation::Rotate270 => DMDO_270,
+    }
+}
+
+fn from_orientation(orientation: DEVMODE_DISPLAY_ORIENTATION) -> Rotation {
+    match orientation {
+        DMDO_90 => Rotation::Rotate90,
+        DMDO_180 => Rotation::Rotate180,
+        DMDO_270 => Rotation::Rotate270,
+        _ => Rotation::Normal,
+    }
+}
+
+/// Maps a `ChangeDisplaySettingsExW` result to a `DisplayError`. `mode` is
+/// the resolution being set, when the call was for a single display.
+fn check(
+    result: DISP_CHANGE,
+    device: Option<&str>,
+    mode: Option<&Resolution>,
+) -> Result<(), DisplayError> {
+    match result {
+        DISP_CHANGE_SUCCESSFUL => Ok(()),
+        DISP_CHANGE_BADMODE => Err(match mode {
+            Some(res) => DisplayError::mode_not_supported(res, device),
+            None => DisplayError::ModeNotSupported {
+                mode: "requested layout".to_string(),
+                device: device.map(str::to_string),
+            },
+        }),
+        DISP_CHANGE_RESTART => Err(DisplayError::RestartRequired),
+        DISP_CHANGE_BADFLAGS => Err(DisplayError::BadFlags),
+        DISP_CHANGE_BADPARAM | DISP_CHANGE_BADDUALVIEW => Err(DisplayError::BadParams {
+            message: format!("ChangeDisplaySettings rejected the settings: {:?}", result),
+        }),
+        _ => Err(format!("ChangeDisplaySettings failed with code: {:?}", result).into()),
+    }
+}
+
+impl GdiBackend {
+    fn outputs(&self) -> Vec<Output> {
+        let mut outputs = Vec::new();
+        let mut index = 0;
+
+        loop {
+            let mut adapter = DISPLAY_DEVICEW {
+                cb: std::mem::size_of::<DISPLAY_DEVICEW>() as u32,
+                ..Default::default()
+            };
+            if !unsafe { EnumDisplayDevicesW(PCWSTR::null(), index, &mut adapter, 0) }.as_bool() {
+                break;
+            }
+            index += 1;
+
+            let attached = adapter
+                .StateFlags
+                .contains(DISPLAY_DEVICE_ATTACHED_TO_DESKTOP);
+            let device_name = from_wide(&adapter.DeviceName);
+            let device = Device::new(Some(&device_name));
+
+            // The first child of an adapter is the monitor plugged into it. Asking
+            // for the interface name gives a DeviceID that encodes the port.
+            let mut monitor = DISPLAY_DEVICEW {
+                cb: std::mem::size_of::<DISPLAY_DEVICEW>() as u32,
+                ..Default::default()
+            };
+            let has_monitor = unsafe {
+                EnumDisplayDevicesW(
+                    device.as_pcwstr(),
+                    0,
+                    &mut monitor,
+                    EDD_GET_DEVICE_INTERFACE_NAME,
+                )
+            }
+            .as_bool();
+
+            // Detached adapters without a monitor are just unused GPU outputs.
+            if !attached && !has_monitor {
+                continue;
+            }
+
+            let (name, connector, edid) = if has_monitor {
+                let interface = from_wide(&monitor.DeviceID);
+                let edid = read_edid(&interface);
+                (from_wide(&monitor.DeviceString), interface, edid)
+            } else {
+                (from_wide(&adapter.DeviceString), device_name.clone(), None)
+            };
+
+            outputs.push(Output {
+                info: DisplayInfo {
+                    connector,
+                    monitor: edid.as_deref().and_then(edid::monitor_id),
+                    name: edid.as_deref().and_then(edid::monitor_name).unwrap_or(name),
+                    device_name,
+                    primary: adapter.StateFlags.contains(DISPLAY_DEVICE_PRIMARY_DEVICE),
+                },
+                attached,
+            });
+        }
+
+        outputs
+    }
+
+    /// The registry settings of every output, as read before staging a topology.
+    fn stored_layout(&self) -> Vec<StoredOutput> {
+        self.outputs()
+            .into_iter()
+            .filter_map(|output| {
+                let device = Device::new(Some(&output.info.device_name));
+                let mut dev_mode = DEVMODEW {
+                    dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+                    ..Default::default()
+                };
+                unsafe {
+                    EnumDisplaySettingsW(device.as_pcwstr(), ENUM_REGISTRY_SETTINGS, &mut dev_mode)
+                }
+                .as_bool()
+                .then_some(StoredOutput {
+                    device,
+                    dev_mode,
+                    primary: output.info.primary,
+                })
+            })
+            .collect()
+    }
+
+    /// Writes `saved` back to the registry without applying it, dropping
+    /// anything staged since it was read.
+    fn restore_stored_layout(&self, saved: &[StoredOutput]) {
+        for output in saved {
+            let mut flags = CDS_UPDATEREGISTRY | CDS_NORESET;
+            if output.primary {
+                flags |= CDS_SET_PRIMARY;
+            }
+            let result = unsafe {
+                ChangeDisplaySettingsExW(
+                    output.device.as_pcwstr(),
+                    Some(&output.dev_mode),
+                    None,
+                    flags,
+                    None,
+                )
+            };
+            if result != DISP_CHANGE_SUCCESSFUL {
+                log::warn!("Failed to restore stored display settings: {:?}", result);
+            }
+        }
+    }
+
+    /// Like `find_mode`, but `res` is in the panel's native orientation and the
+    /// driver may list the mode either way round.
+    fn find_native_mode(&self, device: &Device, res: &Resolution) -> Option<DEVMODEW> {
+        let swapped = Resolution {
+            width: res.height,
+            height: res.width,
+            ..res.clone()
+        };
+        self.find_mode(device, res)
+            .or_else(|| self.find_mode(device, &swapped))
+    }
+
+    fn find_mode(&self, device: &Device, res: &Resolution) -> Option<DEVMODEW> {
+        let mut dev_mode = DEVMODEW::default();
+        let mut mode_num = 0;
+
+        unsafe {
+            // ENUM_DISPLAY_SETTINGS_MODE is a newtype wrapper around u32 in newer windows-rs
+            while EnumDisplaySettingsW(
+                device.as_pcwstr(),
+                ENUM_DISPLAY_SETTINGS_MODE(mode_num),
+                &mut dev_mode,
+            )
+            .as_bool()
+            {
+                if to_resolution(&dev_mode).same_mode(res) {
+                    return Some(dev_mode);
+                }
+                mode_num += 1;
+            }
+        }
+
+        None
+    }
+
+    fn change(
+        &self,
+        device: Option<&str>,
+        res: &Resolution,
+        flags: CDS_TYPE,
+    ) -> Result<(), DisplayError> {
+        let name = device;
+        let device = Device::new(device);
+        // With a rotation the mode is in native orientation; without one it is
+        // taken as listed for the current orientation.
+        let found = match res.rotation {
+            Some(_) => self.find_native_mode(&device, res),
+            None => self.find_mode(&device, res),
+        };
+        let Some(mut target_mode) = found else {
+            return Err(DisplayError::mode_not_supported(res, name));
+        };
+        apply_format(&mut target_mode, res);
+        if let Some(rotation) = res.rotation {
+            let (width, height) = res.desktop_size();
+            target_mode.dmPelsWidth = width;
+            target_mode.dmPelsHeight = height;
+            target_mode.Anonymous1.Anonymous2.dmDisplayOrientation = to_orientation(rotation);
+            target_mode.dmFields |= DM_PELSWIDTH | DM_PELSHEIGHT | DM_DISPLAYORIENTATION;
+        }
+
+        let result = unsafe {
+            ChangeDisplaySettingsExW(device.as_pcwstr(), Some(&target_mode), None, flags, None)
+        };
+        check(result, name, Some(res))
+    }
+}
+
+impl DisplayBackend for GdiBackend {
+    fn name(&self) -> &'static str {
+        "gdi"
+    }
+
+    fn displays(&self) -> Vec<DisplayInfo> {
+        self.outputs()
+            .into_iter()
+            .filter(|o| o.attached)
+            .map(|o| o.info)
+            .collect()
+    }
+
+    fn enumerate_modes(&self, device: Option<&str>) -> Vec<Resolution> {
+        let device = Device::new(device);
+        let mut modes = Vec::new();
+        let mut dev_mode = DEVMODEW::default();
+        let mut mode_num = 0;
+
+        unsafe {
+            while EnumDisplaySettingsW(
+                device.as_pcwstr(),
+                ENUM_DISPLAY_SETTINGS_MODE(mode_num),
+                &mut dev_mode,
+            )
+            .as_bool()
+            {
+                modes.push(to_resolution(&dev_mode));
+                mode_num += 1;
+            }
+        }
+
+        modes
+    }
+
+    fn current_mode(&self, device: Option<&str>) -> Option<Resolution> {
+        let device = Device::new(device);
+        let mut dev_mode = DEVMODEW::default();
+        unsafe {
+            if EnumDisplaySettingsW(device.as_pcwstr(), ENUM_CURRENT_SETTINGS, &mut dev_mode)
+                .as_bool()
+            {
+                // The running mode always has a scaling and orientation; report
+                // them explicitly so restoring this mode restores them too.
+                let mut res = to_resolution(&dev_mode);
+                res.scaling.get_or_insert(Scaling::AspectPreserving);
+                let rotation =
+                    from_orientation(dev_mode.Anonymous1.Anonymous2.dmDisplayOrientation);
+                if rotation.is_sideways() {
+                    std::mem::swap(&mut res.width, &mut res.height);
+                }
+                res.rotation = Some(rotation);
+                Some(res)
+            } else {
+                None
+            }
+        }
+    }
+
+    fn apply_mode(
+        &self,
+        device: Option<&str>,
+        res: &Resolution,
+        persistence: Persistence,
+    ) -> Result<(), DisplayError> {
+        let flags = match persistence {
+            Persistence::Temporary => CDS_FULLSCREEN,
+            Persistence::UpdateRegistry => CDS_UPDATEREGISTRY,
+            Persistence::Global => CDS_UPDATEREGISTRY | CDS_GLOBAL,
+        };
+        self.change(device, res, flags)
+    }
+
+    fn test_mode(&self, device: Option<&str>, res: &Resolution) -> Result<(), DisplayError> {
+        self.change(device, res, CDS_TEST)
+    }
+
+    fn topology(&self) -> Result<Topology, DisplayError> {
+        let mut outputs = Vec::new();
+
+        for output in self.outputs() {
+            let device = Device::new(Some(&output.info.device_name));
+            // Detached outputs remember their last settings in the registry.
+            let which = if output.attached {
+                ENUM_CURRENT_SETTINGS
+            } else {
+                ENUM_REGISTRY_SETTINGS
+            };
+            let mut dev_mode = DEVMODEW::default();
+            let found =
+                unsafe { EnumDisplaySettingsW(device.as_pcwstr(), which, &mut dev_mode) }.as_bool();
+
+            let (mode, x, y, rotation) = if found {
+                let display = unsafe { dev_mode.Anonymous1.Anonymous2 };
+                let rotation = from_orientation(display.dmDisplayOrientation);
+                let mut mode = to_resolution(&dev_mode);
+                if rotation.is_sideways() {
+                    std::mem::swap(&mut mode.width, &mut mode.height);
+                }
+                (mode, display.dmPosition.x, display.dmPosition.y, rotation)
+            } else {
+                (Resolution::default(), 0, 0, Rotation::Normal)
+            };
+
+            outputs.push(OutputLayout {
+                display: output.info.target(),
+                enabled: output.attached,
+                primary: output.info.primary,
+                mode,
+                x,
+                y,
+                rotation,
+            });
+        }
+
+        Ok(Topology { outputs })
+    }
+
+    fn apply_topology(
+        &self,
+        topology: &Topology,
+        persistence: Persistence,
+    ) -> Result<(), DisplayError> {
+        // Windows requires the primary display at the desktop origin.
+        let (origin_x, origin_y) = topology
+            .outputs
+            .iter()
+            .find(|o| o.enabled && o.primary)
+            .map(|o| (o.x, o.y))
+            .unwrap_or((0, 0));
+
+        // Build every DEVMODE before staging anything so a missing mode fails cleanly.
+        let mut staged = Vec::new();
+        for layout in &topology.outputs {
+            let device = Device::new(Some(&layout.display.device_name));
+            let dev_mode = if layout.enabled {
+                let Some(mut dev_mode) = self.find_native_mode(&device, &layout.mode) else {
+                    return Err(DisplayError::mode_not_supported(
+                        &layout.mode,
+                        Some(&layout.display.device_name),
+                    ));
+                };
+                let (width, height) = if layout.rotation.is_sideways() {
+                    (layout.mode.height, layout.mode.width)
+                } else {
+                    (layout.mode.width, layout.mode.height)
+                };
+                dev_mode.dmPelsWidth = width;
+                dev_mode.dmPelsHeight = height;
+                dev_mode.Anonymous1.Anonymous2.dmPosition.x = layout.x - origin_x;
+                dev_mode.Anonymous1.Anonymous2.dmPosition.y = layout.y - origin_y;
+                dev_mode.Anonymous1.Anonymous2.dmDisplayOrientation =
+                    to_orientation(layout.rotation);
+                apply_format(&mut dev_mode, &layout.mode);
+                dev_mode.dmFields |= DM_PELSWIDTH
+                    | DM_PELSHEIGHT
+                    | DM_DISPLAYFREQUENCY
+                    | DM_POSITION
+                    | DM_DISPLAYORIENTATION;
+                dev_mode
+            } else {
+                // A zero-sized mode detaches the display from the desktop.
+                DEVMODEW {
+                    dmSize: std::mem::size_of::<DEVMODEW>() as u16,
+                    dmFields: DM_PELSWIDTH | DM_PELSHEIGHT | DM_POSITION,
+                    ..Default::default()
+                }
+            };
+
+            // Staging goes through the registry whatever the persistence;
+            // a temporary layout has its registry entries put back below.
+            let mut flags = CDS_UPDATEREGISTRY | CDS_NORESET;
+            if persistence == Persistence::Global {
+                flags |= CDS_GLOBAL;
+            }
+            if layout.enabled && layout.primary {
+                flags |= CDS_SET_PRIMARY;
+            }
+            staged.push((layout, device, dev_mode, flags));
+        }
+
+        let saved = self.stored_layout();
+
+        // Stage each display with CDS_NORESET, then apply them all at once.
+        for (layout, device, dev_mode, flags) in &staged {
+            let result = unsafe {
+                ChangeDisplaySettingsExW(device.as_pcwstr(), Some(dev_mode), None, *flags, None)
+            };
+            let checked = check(
+                result,
+                Some(&layout.display.device_name),
+                layout.enabled.then_some(&layout.mode),
+            );
+            if let Err(e) = checked {
+                // Otherwise the next mode change would commit half the layout.
+                self.restore_stored_layout(&saved);
+                return Err(e);
+            }
+        }
+
+        log::info!(
+            "Applying GDI topology with {} output(s)",
+            topology.outputs.len()
+        );
+        let result =
+            unsafe { ChangeDisplaySettingsExW(PCWSTR::null(), None, None, CDS_TYPE(0), None) };
+        let applied = check(result, None, None);
+        if applied.is_err() || persistence == Persistence::Temporary {
+            // Leaves the new layout running but not stored.
+            self.restore_stored_layout(&saved);
+        }
+        applied
+    }
+}
@@ -0,0 +1,555 @@
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_registry::WlRegistry;
+use wayland_client::{event_created_child, Connection, Dispatch, EventQueue, Proxy, QueueHandle};
+use wayland_protocols_plasma::output_device::v2::client::{
+    kde_output_device_mode_v2::{self, KdeOutputDeviceModeV2},
+    kde_output_device_v2::{self, KdeOutputDeviceV2},
+};
+use wayland_protocols_plasma::output_management::v2::client::{
+    kde_output_configuration_v2::{self, KdeOutputConfigurationV2},
+    kde_output_management_v2::KdeOutputManagementV2,
+};
+
+use super::{
+    DisplayBackend, DisplayError, DisplayInfo, MonitorId, OutputLayout, Persistence, Resolution,
+    Rotation, Topology,
+};
+
+/// Wayland backend for KWin using the KDE output-device / output-management
+/// protocols (v2).
+///
+/// Displays are output devices addressed by connector name. KWin's primary
+/// output is not tracked, so the first enabled device stands in for it.
+pub struct KdeOutputBackend;
+
+struct Device {
+    proxy: KdeOutputDeviceV2,
+    name: String,
+    make: String,
+    model: String,
+    eisa_id: String,
+    serial_number: String,
+    enabled: bool,
+    modes: Vec<KdeOutputDeviceModeV2>,
+    current_mode: Option<KdeOutputDeviceModeV2>,
+    position: (i32, i32),
+    /// wl_output transform value.
+    transform: i32,
+    done: bool,
+}
+
+struct Mode {
+    proxy: KdeOutputDeviceModeV2,
+    width: i32,
+    height: i32,
+    /// Refresh rate in mHz.
+    refresh: i32,
+    preferred: bool,
+}
+
+#[derive(Default)]
+struct OutputState {
+    devices: Vec<Device>,
+    modes: Vec<Mode>,
+    outcome: Option<Result<(), DisplayError>>,
+}
+
+impl OutputState {
+    fn mode(&self, proxy: &KdeOutputDeviceModeV2) -> Option<&Mode> {
+        self.modes.iter().find(|m| m.proxy == *proxy)
+    }
+
+    fn target_device(&self, device: Option<&str>) -> Result<&Device, DisplayError> {
+        let mut enabled = self.devices.iter().filter(|d| d.enabled);
+        match device {
+            Some(name) => enabled
+                .find(|d| d.name == name)
+                .ok_or_else(|| DisplayError::device_not_found(name)),
+            None => enabled
+                .next()
+                .ok_or_else(|| "No enabled KDE output device found".to_string().into()),
+        }
+    }
+
+    fn find_mode(&self, device: &Device, res: &Resolution) -> Option<&Mode> {
+        device
+            .modes
+            .iter()
+            .filter_map(|proxy| self.mode(proxy))
+            .find(|m| to_resolution(m).same_mode(res))
+    }
+
+    /// Current mode of an enabled device, or the preferred one of a disabled device.
+    fn layout_mode(&self, device: &Device) -> Option<&Mode> {
+        device
+            .current_mode
+            .as_ref()
+            .and_then(|proxy| self.mode(proxy))
+            .or_else(|| {
+                device
+                    .modes
+                    .iter()
+                    .filter_map(|proxy| self.mode(proxy))
+                    .find(|m| m.preferred)
+            })
+    }
+}
+
+/// Every bound output device plus the management global, on a connection of
+/// their own.
+///
+/// Neither interface has a destructor request, so the compositor only frees
+/// them when the client disconnects; a session therefore never shares its
+/// connection and closes it when dropped.
+struct Session {
+    conn: Connection,
+    queue: EventQueue<OutputState>,
+    state: OutputState,
+    management: KdeOutputManagementV2,
+}
+
+impl Session {
+    fn open() -> Result<Self, String> {
+        let conn = Connection::connect_to_env().map_err(|e| e.to_string())?;
+        let (globals, mut queue) =
+            registry_queue_init::<OutputState>(&conn).map_err(|e| e.to_string())?;
+        let qh = queue.handle();
+        let management = globals
+            .bind::<KdeOutputManagementV2, _, _>(&qh, 1..=2, ())
+            .map_err(|e| format!("kde_output_management_v2 not available: {}", e))?;
+
+        // Output devices are one global per output, so bind each of them.
+        let interface = KdeOutputDeviceV2::interface().name;
+        let devices = globals.contents().with_list(|list| {
+            list.iter()
+                .filter(|g| g.interface == interface)
+                .map(|g| (g.name, g.version))
+                .collect::<Vec<_>>()
+        });
+
+        let mut state = OutputState::default();
+        for (name, version) in devices {
+            let proxy =
+                globals
+                    .registry()
+                    .bind::<KdeOutputDeviceV2, _, _>(name, version.min(2), &qh, ());
+            state.devices.push(Device {
+                proxy,
+                name: String::new(),
+                make: String::new(),
+                model: String::new(),
+                eisa_id: String::new(),
+                serial_number: String::new(),
+                enabled: false,
+                modes: Vec::new(),
+                current_mode: None,
+                position: (0, 0),
+                transform: 0,
+                done: false,
+            });
+        }
+
+        while !state.devices.iter().all(|d| d.done) {
+            queue.roundtrip(&mut state).map_err(|e| e.to_string())?;
+        }
+
+        Ok(Session {
+            conn,
+            queue,
+            state,
+            management,
+        })
+    }
+
+    fn apply(&mut self, device: Option<&str>, res: &Resolution) -> Result<(), DisplayError> {
+        let qh = self.queue.handle();
+        let device = self.state.target_device(device)?;
+        if let Some(scaling) = res.scaling {
+            return Err(DisplayError::scaling_not_supported(&device.name, scaling));
+        }
+        let Some(mode) = self.state.find_mode(device, res) else {
+            return Err(DisplayError::mode_not_supported(res, Some(&device.name)));
+        };
+
+        log::info!(
+            "Applying {}x{}@{} to KDE output {}",
+            res.width,
+            res.height,
+            res.frequency,
+            device.name
+        );
+
+        let config = self.management.create_configuration(&qh, ());
+        config.mode(&device.proxy, &mode.proxy);
+        if let Some(rotation) = res.rotation {
+            config.transform(&device.proxy, to_transform(rotation));
+        }
+        self.submit(config)
+    }
+
+    /// Applies every output of `topology` through a single configuration.
+    fn apply_topology(&mut self, topology: &Topology) -> Result<(), DisplayError> {
+        let qh = self.queue.handle();
+        let config = self.management.create_configuration(&qh, ());
+
+        for layout in &topology.outputs {
+            let name = &layout.display.device_name;
+            let Some(device) = self.state.devices.iter().find(|d| &d.name == name) else {
+                config.destroy();
+                return Err(DisplayError::device_not_found(name));
+            };
+
+            config.enable(&device.proxy, layout.enabled as i32);
+            if !layout.enabled {
+                continue;
+            }
+            if let Some(scaling) = layout.mode.scaling {
+                config.destroy();
+                return Err(DisplayError::scaling_not_supported(name, scaling));
+            }
+            let Some(mode) = self.state.find_mode(device, &layout.mode) else {
+                config.destroy();
+                return Err(DisplayError::mode_not_supported(&layout.mode, Some(name)));
+            };
+            config.mode(&device.proxy, &mode.proxy);
+            config.position(&device.proxy, layout.x, layout.y);
+            config.transform(&device.proxy, to_transform(layout.rotation));
+            if layout.primary && config.version() >= 2 {
+                config.set_primary_output(&device.proxy);
+            }
+        }
+
+        log::info!(
+            "Applying KDE topology with {} output(s)",
+            topology.outputs.len()
+        );
+        self.submit(config)
+    }
+
+    /// Applies `config` and waits for KWin's verdict.
+    fn submit(&mut self, config: KdeOutputConfigurationV2) -> Result<(), DisplayError> {
+        config.apply();
+
+        self.state.outcome = None;
+        while self.state.outcome.is_none() {
+            self.queue
+                .blocking_dispatch(&mut self.state)
+                .map_err(|e| e.to_string())?;
+        }
+        config.destroy();
+
+        self.state.outcome.take().unwrap_or(Ok(()))
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        // Send what is still queued; the socket closes once the fields are
+        // dropped, which releases every object bound through it.
+        let _ = self.conn.flush();
+    }
+}
+
+impl KdeOutputBackend {
+    pub fn connect() -> Result<Self, String> {
+        // Fail early if the compositor does not speak the protocol.
+        Session::open()?;
+        Ok(KdeOutputBackend)
+    }
+}
+
+impl DisplayBackend for KdeOutputBackend {
+    fn name(&self) -> &'static str {
+        "kde-output-management"
+    }
+
+    fn displays(&self) -> Vec<DisplayInfo> {
+        let session = match Session::open() {
+            Ok(session) => session,
+            Err(e) => {
+                log::error!("Failed to list KDE output devices: {}", e);
+                return Vec::new();
+            }
+        };
+
+        session
+            .state
+            .devices
+            .iter()
+            .filter(|d| d.enabled)
+            .enumerate()
+            .map(|(index, device)| device_info(device, index == 0))
+            .collect()
+    }
+
+    fn enumerate_modes(&self, device: Option<&str>) -> Vec<Resolution> {
+        let session = match Session::open() {
+            Ok(session) => session,
+            Err(e) => {
+                log::error!("Failed to enumerate KDE output modes: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let Ok(device) = session.state.target_device(device) else {
+            return Vec::new();
+        };
+        device
+            .modes
+            .iter()
+            .filter_map(|proxy| session.state.mode(proxy))
+            .map(to_resolution)
+            .collect()
+    }
+
+    fn current_mode(&self, device: Option<&str>) -> Option<Resolution> {
+        let session = Session::open().ok()?;
+        let device = session.state.target_device(device).ok()?;
+        let current = device.current_mode.as_ref()?;
+        session.state.mode(current).map(|mode| Resolution {
+            rotation: Some(from_transform(device.transform)),
+            ..to_resolution(mode)
+        })
+    }
+
+    fn apply_mode(
+        &self,
+        device: Option<&str>,
+        res: &Resolution,
+        _persistence: Persistence,
+    ) -> Result<(), DisplayError> {
+        // KWin stores every applied configuration; there is no temporary mode.
+        Session::open()?.apply(device, res)
+    }
+
+    fn test_mode(&self, device: Option<&str>, res: &Resolution) -> Result<(), DisplayError> {
+        // kde_output_configuration_v2 has no test request, so only check the
+        // mode is advertised by the device.
+        let session = Session::open()?;
+        let device = session.state.target_device(device)?;
+        if let Some(scaling) = res.scaling {
+            return Err(DisplayError::scaling_not_supported(&device.name, scaling));
+        }
+        match session.state.find_mode(device, res) {
+            Some(_) => Ok(()),
+            None => Err(DisplayError::mode_not_supported(res, Some(&device.name))),
+        }
+    }
+
+    fn topology(&self) -> Result<Topology, DisplayError> {
+        let session = Session::open()?;
+        let state = &session.state;
+        let first_enabled = state.devices.iter().position(|d| d.enabled);
+
+        let outputs = state
+            .devices
+            .iter()
+            .enumerate()
+            .map(|(index, device)| OutputLayout {
+                display: device_info(device, Some(index) == first_enabled).target(),
+                enabled: device.enabled,
+                primary: Some(index) == first_enabled,
+                mode: state
+                    .layout_mode(device)
+                    .map(to_resolution)
+                    .unwrap_or_default(),
+                x: device.position.0,
+                y: device.position.1,
+                rotation: from_transform(device.transform),
+            })
+            .collect();
+
+        Ok(Topology { outputs })
+    }
+
+    fn apply_topology(
+        &self,
+        topology: &Topology,
+        _persistence: Persistence,
+    ) -> Result<(), DisplayError> {
+        Session::open()?.apply_topology(topology)
+    }
+}
+
+fn device_info(device: &Device, primary: bool) -> DisplayInfo {
+    // KWin sends the EISA id from the EDID; fall back to the make for
+    // outputs without one (virtual or built-in panels).
+    let manufacturer = if device.eisa_id.is_empty() {
+        device.make.clone()
+    } else {
+        device.eisa_id.clone()
+    };
+    let monitor = (!manufacturer.is_empty() || !device.model.is_empty()).then(|| MonitorId {
+        manufacturer,
+        product: device.model.clone(),
+        serial: (!device.serial_number.is_empty()).then(|| device.serial_number.clone()),
+    });
+    let name = format!("{} {}", device.make, device.model);
+    DisplayInfo {
+        device_name: device.name.clone(),
+        connector: device.name.clone(),
+        monitor,
+        name: if name.trim().is_empty() {
+            device.name.clone()
+        } else {
+            name.trim().to_string()
+        },
+        primary,
+    }
+}
+
+/// KDE uses the wl_output transform values, which count counter-clockwise.
+fn to_transform(rotation: Rotation) -> i32 {
+    match rotation {
+        Rotation::Normal => 0,
+        Rotation::Rotate90 => 3,
+        Rotation::Rotate180 => 2,
+        Rotation::Rotate270 => 1,
+    }
+}
+
+fn from_transform(transform: i32) -> Rotation {
+    // Flipped variants (4-7) rotate the same way as their unflipped pair.
+    match transform % 4 {
+        1 => Rotation::Rotate270,
+        2 => Rotation::Rotate180,
+        3 => Rotation::Rotate90,
+        _ => Rotation::Normal,
+    }
+}
+
+fn to_resolution(mode: &Mode) -> Resolution {
+    Resolution::new(
+        mode.width.max(0) as u32,
+        mode.height.max(0) as u32,
+        ((mode.refresh.max(0) + 500) / 1000) as u32,
+    )
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for OutputState {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<KdeOutputManagementV2, ()> for OutputState {
+    fn event(
+        _: &mut Self,
+        _: &KdeOutputManagementV2,
+        _: <KdeOutputManagementV2 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<KdeOutputDeviceV2, ()> for OutputState {
+    fn event(
+        state: &mut Self,
+        proxy: &KdeOutputDeviceV2,
+        event: kde_output_device_v2::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let Some(device) = state.devices.iter_mut().find(|d| d.proxy == *proxy) else {
+            return;
+        };
+        match event {
+            kde_output_device_v2::Event::Name { name } => device.name = name,
+            kde_output_device_v2::Event::Geometry {
+                x,
+                y,
+                make,
+                model,
+                transform,
+                ..
+            } => {
+                device.position = (x, y);
+                device.make = make;
+                device.model = model;
+                device.transform = transform;
+            }
+            kde_output_device_v2::Event::EisaId { eisaId } => device.eisa_id = eisaId,
+            kde_output_device_v2::Event::SerialNumber { serialNumber } => {
+                device.serial_number = serialNumber
+            }
+            kde_output_device_v2::Event::Enabled { enabled } => device.enabled = enabled != 0,
+            kde_output_device_v2::Event::Mode { mode } => {
+                device.modes.push(mode.clone());
+                state.modes.push(Mode {
+                    proxy: mode,
+                    width: 0,
+                    height: 0,
+                    refresh: 0,
+                    preferred: false,
+                });
+            }
+            kde_output_device_v2::Event::CurrentMode { mode } => device.current_mode = Some(mode),
+            kde_output_device_v2::Event::Done => device.done = true,
+            _ => {}
+        }
+    }
+
+    event_created_child!(OutputState, KdeOutputDeviceV2, [
+        kde_output_device_v2::EVT_MODE_OPCODE => (KdeOutputDeviceModeV2, ()),
+    ]);
+}
+
+impl Dispatch<KdeOutputDeviceModeV2, ()> for OutputState {
+    fn event(
+        state: &mut Self,
+        proxy: &KdeOutputDeviceModeV2,
+        event: kde_output_device_mode_v2::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let kde_output_device_mode_v2::Event::Removed = event {
+            state.modes.retain(|m| m.proxy != *proxy);
+            for device in &mut state.devices {
+                device.modes.retain(|m| m != proxy);
+            }
+            return;
+        }
+
+        let Some(mode) = state.modes.iter_mut().find(|m| m.proxy == *proxy) else {
+            return;
+        };
+        match event {
+            kde_output_device_mode_v2::Event::Size { width, height } => {
+                mode.width = width;
+                mode.height = height;
+            }
+            kde_output_device_mode_v2::Event::Refresh { refresh } => mode.refresh = refresh,
+            kde_output_device_mode_v2::Event::Preferred => mode.preferred = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<KdeOutputConfigurationV2, ()> for OutputState {
+    fn event(
+        state: &mut Self,
+        _: &KdeOutputConfigurationV2,
+        event: kde_output_configuration_v2::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        state.outcome = match event {
+            kde_output_configuration_v2::Event::Applied => Some(Ok(())),
+            kde_output_configuration_v2::Event::Failed => Some(Err(DisplayError::BadParams {
+                message: "KWin rejected the output configuration".to_string(),
+            })),
+            _ => return,
+        };
+    }
+}
@@ -0,0 +1,629 @@
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_output::Transform;
+use wayland_client::protocol::wl_registry::WlRegistry;
+use wayland_client::{
+    event_created_child, Connection, Dispatch, EventQueue, Proxy, QueueHandle, WEnum,
+};
+use wayland_protocols_wlr::output_management::v1::client::{
+    zwlr_output_configuration_head_v1::ZwlrOutputConfigurationHeadV1,
+    zwlr_output_configuration_v1::{self, ZwlrOutputConfigurationV1},
+    zwlr_output_head_v1::{self, ZwlrOutputHeadV1},
+    zwlr_output_manager_v1::{self, ZwlrOutputManagerV1},
+    zwlr_output_mode_v1::{self, ZwlrOutputModeV1},
+};
+
+use super::{
+    DisplayBackend, DisplayError, DisplayInfo, MonitorId, OutputLayout, Persistence, Resolution,
+    Rotation, Topology,
+};
+
+/// Wayland backend for wlroots-based compositors (sway, Hyprland, river, ...)
+/// using `zwlr_output_manager_v1`.
+///
+/// Displays are heads addressed by connector name (`DP-1`, `HDMI-A-1`, ...).
+/// Wayland has no primary output, so the first enabled head stands in for it.
+pub struct WlrOutputBackend {
+    conn: Connection,
+}
+
+struct Head {
+    proxy: ZwlrOutputHeadV1,
+    name: String,
+    description: String,
+    make: String,
+    model: String,
+    serial_number: String,
+    enabled: bool,
+    modes: Vec<ZwlrOutputModeV1>,
+    current_mode: Option<ZwlrOutputModeV1>,
+    position: (i32, i32),
+    transform: Option<Transform>,
+    scale: f64,
+}
+
+struct Mode {
+    proxy: ZwlrOutputModeV1,
+    width: i32,
+    height: i32,
+    /// Refresh rate in mHz.
+    refresh: i32,
+    preferred: bool,
+}
+
+/// What a configuration asks of one head.
+struct HeadSettings {
+    head: ZwlrOutputHeadV1,
+    enabled: bool,
+    mode: Option<ZwlrOutputModeV1>,
+    position: (i32, i32),
+    transform: Option<Transform>,
+    scale: f64,
+}
+
+impl Head {
+    fn settings(&self) -> HeadSettings {
+        HeadSettings {
+            head: self.proxy.clone(),
+            enabled: self.enabled,
+            mode: self.current_mode.clone(),
+            position: self.position,
+            transform: self.transform,
+            scale: self.scale,
+        }
+    }
+}
+
+#[derive(Default)]
+struct OutputState {
+    heads: Vec<Head>,
+    modes: Vec<Mode>,
+    serial: Option<u32>,
+    outcome: Option<Result<(), DisplayError>>,
+}
+
+impl OutputState {
+    fn mode(&self, proxy: &ZwlrOutputModeV1) -> Option<&Mode> {
+        self.modes.iter().find(|m| m.proxy == *proxy)
+    }
+
+    fn target_head(&self, device: Option<&str>) -> Result<&Head, DisplayError> {
+        let mut enabled = self.heads.iter().filter(|h| h.enabled);
+        match device {
+            Some(name) => enabled
+                .find(|h| h.name == name)
+                .ok_or_else(|| DisplayError::device_not_found(name)),
+            None => enabled
+                .next()
+                .ok_or_else(|| "No enabled Wayland output found".to_string().into()),
+        }
+    }
+
+    fn find_mode(&self, head: &Head, res: &Resolution) -> Option<&Mode> {
+        head.modes
+            .iter()
+            .filter_map(|proxy| self.mode(proxy))
+            .find(|m| to_resolution(m).same_mode(res))
+    }
+
+    /// Current mode of an enabled head, or the preferred one of a disabled head.
+    fn layout_mode(&self, head: &Head) -> Option<&Mode> {
+        head.current_mode
+            .as_ref()
+            .and_then(|proxy| self.mode(proxy))
+            .or_else(|| {
+                head.modes
+                    .iter()
+                    .filter_map(|proxy| self.mode(proxy))
+                    .find(|m| m.preferred)
+            })
+    }
+}
+
+/// One bound output manager together with the state it has reported so far.
+struct Session {
+    queue: EventQueue<OutputState>,
+    state: OutputState,
+    manager: ZwlrOutputManagerV1,
+}
+
+impl Session {
+    fn open(conn: &Connection) -> Result<Self, String> {
+        let (globals, mut queue) =
+            registry_queue_init::<OutputState>(conn).map_err(|e| e.to_string())?;
+        let qh = queue.handle();
+        let manager = globals
+            .bind::<ZwlrOutputManagerV1, _, _>(&qh, 1..=4, ())
+            .map_err(|e| format!("zwlr_output_manager_v1 not available: {}", e))?;
+
+        // The manager sends every head and mode, then `done` with the current serial.
+        let mut state = OutputState::default();
+        while state.serial.is_none() {
+            queue.roundtrip(&mut state).map_err(|e| e.to_string())?;
+        }
+
+        Ok(Session {
+            queue,
+            state,
+            manager,
+        })
+    }
+
+    /// Builds a configuration that keeps every head as it is except for the
+    /// target head's mode (and transform, if `res` has a rotation), then
+    /// either tests or applies it.
+    fn configure(
+        &mut self,
+        device: Option<&str>,
+        res: &Resolution,
+        test_only: bool,
+    ) -> Result<(), DisplayError> {
+        let target = self.state.target_head(device)?;
+        if let Some(scaling) = res.scaling {
+            return Err(DisplayError::scaling_not_supported(&target.name, scaling));
+        }
+        let Some(mode) = self.state.find_mode(target, res) else {
+            return Err(DisplayError::mode_not_supported(res, Some(&target.name)));
+        };
+        let target_head = target.proxy.clone();
+        let target_mode = mode.proxy.clone();
+
+        let settings = self
+            .state
+            .heads
+            .iter()
+            .map(|head| {
+                let mut settings = head.settings();
+                if head.proxy == target_head {
+                    settings.mode = Some(target_mode.clone());
+                    if let Some(rotation) = res.rotation {
+                        settings.transform = Some(to_transform(rotation));
+                    }
+                }
+                settings
+            })
+            .collect::<Vec<_>>();
+        self.submit(&settings, test_only)
+    }
+
+    /// Sends one configuration covering every head; the compositor applies it
+    /// atomically or not at all.
+    fn submit(&mut self, settings: &[HeadSettings], test_only: bool) -> Result<(), DisplayError> {
+        let qh = self.queue.handle();
+        let serial = self.state.serial.unwrap_or_default();
+        let config = self.manager.create_configuration(serial, &qh, ());
+        for head in settings {
+            if !head.enabled {
+                config.disable_head(&head.head);
+                continue;
+            }
+
+            let head_config = config.enable_head(&head.head, &qh, ());
+            if let Some(mode) = &head.mode {
+                head_config.set_mode(mode);
+            }
+            head_config.set_position(head.position.0, head.position.1);
+            if let Some(transform) = head.transform {
+                head_config.set_transform(transform);
+            }
+            head_config.set_scale(head.scale);
+        }
+
+        if test_only {
+            config.test();
+        } else {
+            config.apply();
+        }
+
+        self.state.outcome = None;
+        while self.state.outcome.is_none() {
+            self.queue
+                .blocking_dispatch(&mut self.state)
+                .map_err(|e| e.to_string())?;
+        }
+        config.destroy();
+
+        self.state.outcome.take().unwrap_or(Ok(()))
+    }
+}
+
+impl Drop for Session {
+    /// Each backend call opens a session on the shared connection, so its head
+    /// and mode objects must go with it or they pile up for the connection's
+    /// life. Before manager version 3 they have no destructor request.
+    fn drop(&mut self) {
+        for mode in self.state.modes.drain(..) {
+            release_mode(&mode.proxy);
+        }
+        for head in self.state.heads.drain(..) {
+            release_head(&head.proxy);
+        }
+        self.manager.stop();
+        let _ = self.queue.flush();
+    }
+}
+
+impl WlrOutputBackend {
+    pub fn connect() -> Result<Self, String> {
+        let conn = Connection::connect_to_env().map_err(|e| e.to_string())?;
+        // Fail early if the compositor does not speak the protocol.
+        Session::open(&conn)?;
+        Ok(WlrOutputBackend { conn })
+    }
+}
+
+impl DisplayBackend for WlrOutputBackend {
+    fn name(&self) -> &'static str {
+        "wlr-output-management"
+    }
+
+    fn displays(&self) -> Vec<DisplayInfo> {
+        let session = match Session::open(&self.conn) {
+            Ok(session) => session,
+            Err(e) => {
+                log::error!("Failed to list Wayland outputs: {}", e);
+                return Vec::new();
+            }
+        };
+
+        session
+            .state
+            .heads
+            .iter()
+            .filter(|h| h.enabled)
+            .enumerate()
+            .map(|(index, head)| head_info(head, index == 0))
+            .collect()
+    }
+
+    fn enumerate_modes(&self, device: Option<&str>) -> Vec<Resolution> {
+        let session = match Session::open(&self.conn) {
+            Ok(session) => session,
+            Err(e) => {
+                log::error!("Failed to enumerate Wayland modes: {}", e);
+                return Vec::new();
+            }
+        };
+
+        let Ok(head) = session.state.target_head(device) else {
+            return Vec::new();
+        };
+        head.modes
+            .iter()
+            .filter_map(|proxy| session.state.mode(proxy))
+            .map(to_resolution)
+            .collect()
+    }
+
+    fn current_mode(&self, device: Option<&str>) -> Option<Resolution> {
+        let session = Session::open(&self.conn).ok()?;
+        let head = session.state.target_head(device).ok()?;
+        let current = head.current_mode.as_ref()?;
+        session.state.mode(current).map(|mode| Resolution {
+            rotation: Some(head.transform.map(from_transform).unwrap_or_default()),
+            ..to_resolution(mode)
+        })
+    }
+
+    fn apply_mode(
+        &self,
+        device: Option<&str>,
+        res: &Resolution,
+        persistence: Persistence,
+    ) -> Result<(), DisplayError> {
+        if persistence != Persistence::Temporary {
+            log::warn!(
+                "{} cannot store display modes, applying for this session only",
+                self.name()
+            );
+        }
+        let mut session = Session::open(&self.conn)?;
+        log::info!(
+            "Applying {}x{}@{} to Wayland output {}",
+            res.width,
+            res.height,
+            res.frequency,
+            session.state.target_head(device)?.name
+        );
+        session.configure(device, res, false)
+    }
+
+    fn test_mode(&self, device: Option<&str>, res: &Resolution) -> Result<(), DisplayError> {
+        Session::open(&self.conn)?.configure(device, res, true)
+    }
+
+    fn topology(&self) -> Result<Topology, DisplayError> {
+        let session = Session::open(&self.conn)?;
+        let state = &session.state;
+        let first_enabled = state.heads.iter().position(|h| h.enabled);
+
+        let outputs = state
+            .heads
+            .iter()
+            .enumerate()
+            .map(|(index, head)| OutputLayout {
+                display: head_info(head, Some(index) == first_enabled).target(),
+                enabled: head.enabled,
+                primary: Some(index) == first_enabled,
+                mode: state
+                    .layout_mode(head)
+                    .map(to_resolution)
+                    .unwrap_or_default(),
+                x: head.position.0,
+                y: head.position.1,
+                rotation: head.transform.map(from_transform).unwrap_or_default(),
+            })
+            .collect();
+
+        Ok(Topology { outputs })
+    }
+
+    fn apply_topology(
+        &self,
+        topology: &Topology,
+        persistence: Persistence,
+    ) -> Result<(), DisplayError> {
+        if persistence != Persistence::Temporary {
+            log::warn!(
+                "{} cannot store display layouts, applying for this session only",
+                self.name()
+            );
+        }
+        let mut session = Session::open(&self.conn)?;
+
+        let mut settings = Vec::new();
+        for head in &session.state.heads {
+            let mut head_settings = head.settings();
+            if let Some(layout) = topology
+                .outputs
+                .iter()
+                .find(|l| l.display.device_name == head.name)
+            {
+                head_settings.enabled = layout.enabled;
+                if layout.enabled {
+                    if let Some(scaling) = layout.mode.scaling {
+                        return Err(DisplayError::scaling_not_supported(&head.name, scaling));
+                    }
+                    let mode = session.state.find_mode(head, &layout.mode).ok_or_else(|| {
+                        DisplayError::mode_not_supported(&layout.mode, Some(&head.name))
+                    })?;
+                    head_settings.mode = Some(mode.proxy.clone());
+                    head_settings.position = (layout.x, layout.y);
+                    head_settings.transform = Some(to_transform(layout.rotation));
+                }
+            }
+            settings.push(head_settings);
+        }
+
+        // Wayland has no primary output, so `primary` is not applied.
+        log::info!(
+            "Applying Wayland topology with {} output(s)",
+            topology.outputs.len()
+        );
+        session.submit(&settings, false)
+    }
+}
+
+fn head_info(head: &Head, primary: bool) -> DisplayInfo {
+    // The compositor already decoded the EDID into make/model/serial.
+    let monitor = (!head.make.is_empty() || !head.model.is_empty()).then(|| MonitorId {
+        manufacturer: head.make.clone(),
+        product: head.model.clone(),
+        serial: (!head.serial_number.is_empty()).then(|| head.serial_number.clone()),
+    });
+    DisplayInfo {
+        device_name: head.name.clone(),
+        connector: head.name.clone(),
+        monitor,
+        name: if head.description.is_empty() {
+            head.name.clone()
+        } else {
+            head.description.clone()
+        },
+        primary,
+    }
+}
+
+/// wl_output transforms count counter-clockwise, `Rotation` clockwise.
+fn to_transform(rotation: Rotation) -> Transform {
+    match rotation {
+        Rotation::Normal => Transform::Normal,
+        Rotation::Rotate90 => Transform::_270,
+        Rotation::Rotate180 => Transform::_180,
+        Rotation::Rotate270 => Transform::_90,
+    }
+}
+
+fn from_transform(transform: Transform) -> Rotation {
+    match transform {
+        Transform::_90 | Transform::Flipped90 => Rotation::Rotate270,
+        Transform::_180 | Transform::Flipped180 => Rotation::Rotate180,
+        Transform::_270 | Transform::Flipped270 => Rotation::Rotate90,
+        _ => Rotation::Normal,
+    }
+}
+
+fn to_resolution(mode: &Mode) -> Resolution {
+    Resolution::new(
+        mode.width.max(0) as u32,
+        mode.height.max(0) as u32,
+        ((mode.refresh.max(0) + 500) / 1000) as u32,
+    )
+}
+
+fn release_head(head: &ZwlrOutputHeadV1) {
+    if head.version() >= 3 {
+        head.release();
+    }
+}
+
+fn release_mode(mode: &ZwlrOutputModeV1) {
+    if mode.version() >= 3 {
+        mode.release();
+    }
+}
+
+impl Dispatch<WlRegistry, GlobalListContents> for OutputState {
+    fn event(
+        _: &mut Self,
+        _: &WlRegistry,
+        _: <WlRegistry as Proxy>::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrOutputManagerV1, ()> for OutputState {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrOutputManagerV1,
+        event: zwlr_output_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_output_manager_v1::Event::Head { head } => state.heads.push(Head {
+                proxy: head,
+                name: String::new(),
+                description: String::new(),
+                make: String::new(),
+                model: String::new(),
+                serial_number: String::new(),
+                enabled: false,
+                modes: Vec::new(),
+                current_mode: None,
+                position: (0, 0),
+                transform: None,
+                scale: 1.0,
+            }),
+            zwlr_output_manager_v1::Event::Done { serial } => state.serial = Some(serial),
+            _ => {}
+        }
+    }
+
+    event_created_child!(OutputState, ZwlrOutputManagerV1, [
+        zwlr_output_manager_v1::EVT_HEAD_OPCODE => (ZwlrOutputHeadV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrOutputHeadV1, ()> for OutputState {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrOutputHeadV1,
+        event: zwlr_output_head_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_output_head_v1::Event::Finished = event {
+            state.heads.retain(|h| h.proxy != *proxy);
+            release_head(proxy);
+            return;
+        }
+
+        let Some(head) = state.heads.iter_mut().find(|h| h.proxy == *proxy) else {
+            return;
+        };
+        match event {
+            zwlr_output_head_v1::Event::Name { name } => head.name = name,
+            zwlr_output_head_v1::Event::Description { description } => {
+                head.description = description
+            }
+            zwlr_output_head_v1::Event::Make { make } => head.make = make,
+            zwlr_output_head_v1::Event::Model { model } => head.model = model,
+            zwlr_output_head_v1::Event::SerialNumber { serial_number } => {
+                head.serial_number = serial_number
+            }
+            zwlr_output_head_v1::Event::Enabled { enabled } => head.enabled = enabled != 0,
+            zwlr_output_head_v1::Event::Mode { mode } => {
+                head.modes.push(mode.clone());
+                state.modes.push(Mode {
+                    proxy: mode,
+                    width: 0,
+                    height: 0,
+                    refresh: 0,
+                    preferred: false,
+                });
+            }
+            zwlr_output_head_v1::Event::CurrentMode { mode } => head.current_mode = Some(mode),
+            zwlr_output_head_v1::Event::Position { x, y } => head.position = (x, y),
+            zwlr_output_head_v1::Event::Transform {
+                transform: WEnum::Value(transform),
+            } => head.transform = Some(transform),
+            zwlr_output_head_v1::Event::Scale { scale } => head.scale = scale,
+            _ => {}
+        }
+    }
+
+    event_created_child!(OutputState, ZwlrOutputHeadV1, [
+        zwlr_output_head_v1::EVT_MODE_OPCODE => (ZwlrOutputModeV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrOutputModeV1, ()> for OutputState {
+    fn event(
+        state: &mut Self,
+        proxy: &ZwlrOutputModeV1,
+        event: zwlr_output_mode_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_output_mode_v1::Event::Finished = event {
+            state.modes.retain(|m| m.proxy != *proxy);
+            release_mode(proxy);
+            return;
+        }
+
+        let Some(mode) = state.modes.iter_mut().find(|m| m.proxy == *proxy) else {
+            return;
+        };
+        match event {
+            zwlr_output_mode_v1::Event::Size { width, height } => {
+                mode.width = width;
+                mode.height = height;
+            }
+            zwlr_output_mode_v1::Event::Refresh { refresh } => mode.refresh = refresh,
+            zwlr_output_mode_v1::Event::Preferred => mode.preferred = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationV1, ()> for OutputState {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrOutputConfigurationV1,
+        event: zwlr_output_configuration_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        state.outcome = match event {
+            zwlr_output_configuration_v1::Event::Succeeded => Some(Ok(())),
+            zwlr_output_configuration_v1::Event::Failed => Some(Err(DisplayError::BadParams {
+                message: "Compositor rejected the output configuration".to_string(),
+            })),
+            zwlr_output_configuration_v1::Event::Cancelled => Some(Err(
+                "Output configuration was cancelled because the outputs changed"
+                    .to_string()
+                    .into(),
+            )),
+            _ => return,
+        };
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationHeadV1, ()> for OutputState {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrOutputConfigurationHeadV1,
+        _: <ZwlrOutputConfigurationHeadV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
@@ -0,0 +1,895 @@
+use x11rb::connection::Connection;
+use x11rb::protocol::randr::{self, ConnectionExt as _, ModeFlag, ModeInfo, SetConfig};
+use x11rb::protocol::xproto::{Atom, AtomEnum, ConnectionExt as _, PropMode, Window};
+use x11rb::rust_connection::RustConnection;
+use x11rb::CURRENT_TIME;
+
+use super::{
+    edid, DisplayBackend, DisplayError, DisplayInfo, OutputLayout, Persistence, Resolution,
+    Rotation, Scaling, Topology,
+};
+
+/// X11 backend built on the RandR extension (1.3+).
+///
+/// Displays are RandR outputs addressed by name (`HDMI-1`, `DP-2`, ...). When
+/// no output is given, the primary output is used, or the first connected
+/// output driving a CRTC when no primary is set.
+pub struct XrandrBackend {
+    conn: RustConnection,
+    root: Window,
+    edid_atom: u32,
+    /// The "scaling mode" output property most kernel drivers expose.
+    scaling_atom: u32,
+    /// Physical size of one pixel, used to keep the screen DPI when resizing it.
+    mm_per_px: (f64, f64),
+}
+
+/// Snapshot of the output we are driving, taken at the start of every operation
+/// so the config timestamp is always current.
+struct OutputState {
+    config_timestamp: u32,
+    output: randr::Output,
+    name: String,
+    crtc: randr::Crtc,
+    crtcs: Vec<randr::Crtc>,
+    modes: Vec<ModeInfo>,
+}
+
+/// One output of a topology change and the CRTC it ends up on.
+struct PlannedOutput<'a> {
+    output: randr::Output,
+    layout: &'a OutputLayout,
+    /// CRTC driving the output now, `NONE` if it is off.
+    current_crtc: randr::Crtc,
+    /// CRTC that will drive it, `NONE` if it is being turned off.
+    crtc: randr::Crtc,
+    mode: Option<ModeInfo>,
+    /// Value for the "scaling mode" property, when the layout pins one.
+    scaling: Option<Atom>,
+}
+
+impl XrandrBackend {
+    pub fn connect() -> Result<Self, String> {
+        let (conn, screen_num) = x11rb::connect(None).map_err(|e| e.to_string())?;
+
+        let version = conn
+            .randr_query_version(1, 3)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| format!("RandR not available: {}", e))?;
+        if (version.major_version, version.minor_version) < (1, 3) {
+            return Err(format!(
+                "RandR {}.{} is too old, 1.3 is required",
+                version.major_version, version.minor_version
+            ));
+        }
+
+        let intern = |name: &[u8]| -> Result<Atom, String> {
+            Ok(conn
+                .intern_atom(false, name)
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?
+                .atom)
+        };
+        let edid_atom = intern(b"EDID")?;
+        let scaling_atom = intern(b"scaling mode")?;
+
+        let screen = &conn.setup().roots[screen_num];
+        let root = screen.root;
+        let mm_per_px = (
+            screen.width_in_millimeters as f64 / screen.width_in_pixels.max(1) as f64,
+            screen.height_in_millimeters as f64 / screen.height_in_pixels.max(1) as f64,
+        );
+
+        Ok(XrandrBackend {
+            conn,
+            root,
+            edid_atom,
+            scaling_atom,
+            mm_per_px,
+        })
+    }
+
+    fn resources(&self) -> Result<randr::GetScreenResourcesCurrentReply, String> {
+        self.conn
+            .randr_get_screen_resources_current(self.root)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())
+    }
+
+    fn primary_output(&self) -> Result<randr::Output, String> {
+        Ok(self
+            .conn
+            .randr_get_output_primary(self.root)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?
+            .output)
+    }
+
+    /// Connected outputs that currently drive a CRTC, primary first.
+    fn active_outputs(
+        &self,
+        resources: &randr::GetScreenResourcesCurrentReply,
+    ) -> Result<Vec<(randr::Output, randr::GetOutputInfoReply)>, String> {
+        let primary = self.primary_output()?;
+        let mut candidates = vec![primary];
+        candidates.extend(resources.outputs.iter().copied().filter(|o| *o != primary));
+
+        let mut outputs = Vec::new();
+        for output in candidates {
+            if output == x11rb::NONE {
+                continue;
+            }
+            let info = self
+                .conn
+                .randr_get_output_info(output, resources.config_timestamp)
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?;
+
+            if info.connection == randr::Connection::CONNECTED && info.crtc != x11rb::NONE {
+                outputs.push((output, info));
+            }
+        }
+
+        Ok(outputs)
+    }
+
+    fn output_state(&self, device: Option<&str>) -> Result<OutputState, DisplayError> {
+        let resources = self.resources()?;
+        let outputs = self.active_outputs(&resources)?;
+
+        let found = match device {
+            Some(name) => outputs
+                .into_iter()
+                .find(|(_, info)| info.name == name.as_bytes()),
+            None => outputs.into_iter().next(),
+        };
+        let Some((output, info)) = found else {
+            return Err(match device {
+                Some(name) => DisplayError::device_not_found(name),
+                None => "No active RandR output found".to_string().into(),
+            });
+        };
+
+        let modes = info
+            .modes
+            .iter()
+            .filter_map(|id| resources.modes.iter().find(|m| m.id == *id).copied())
+            .collect();
+
+        Ok(OutputState {
+            config_timestamp: resources.config_timestamp,
+            output,
+            name: String::from_utf8_lossy(&info.name).into_owned(),
+            crtc: info.crtc,
+            crtcs: resources.crtcs,
+            modes,
+        })
+    }
+
+    fn describe(
+        &self,
+        output: randr::Output,
+        info: &randr::GetOutputInfoReply,
+        primary: randr::Output,
+    ) -> DisplayInfo {
+        let device_name = String::from_utf8_lossy(&info.name).into_owned();
+        let edid = self.edid(output);
+        DisplayInfo {
+            // RandR output names are already tied to the physical port.
+            connector: device_name.clone(),
+            monitor: edid.as_deref().and_then(edid::monitor_id),
+            name: edid
+                .as_deref()
+                .and_then(edid::monitor_name)
+                .unwrap_or_else(|| device_name.clone()),
+            device_name,
+            primary: output == primary,
+        }
+    }
+
+    fn edid(&self, output: randr::Output) -> Option<Vec<u8>> {
+        let reply = self
+            .conn
+            .randr_get_output_property(output, self.edid_atom, AtomEnum::ANY, 0, 128, false, false)
+            .ok()?
+            .reply()
+            .ok()?;
+        (!reply.data.is_empty()).then_some(reply.data)
+    }
+
+    /// Current value of the output's "scaling mode" property, if it has one.
+    fn scaling(&self, output: randr::Output) -> Option<Scaling> {
+        let reply = self
+            .conn
+            .randr_get_output_property(
+                output,
+                self.scaling_atom,
+                AtomEnum::ATOM,
+                0,
+                1,
+                false,
+                false,
+            )
+            .ok()?
+            .reply()
+            .ok()?;
+        let bytes: [u8; 4] = reply.data.get(..4)?.try_into().ok()?;
+        let atom = u32::from_ne_bytes(bytes);
+        let name = self.conn.get_atom_name(atom).ok()?.reply().ok()?.name;
+        match name.as_slice() {
+            b"Full" => Some(Scaling::Stretched),
+            b"Center" => Some(Scaling::Centered),
+            b"Full aspect" => Some(Scaling::AspectPreserving),
+            _ => None,
+        }
+    }
+
+    /// The "scaling mode" value for `scaling`, checked against the values the
+    /// output's driver accepts.
+    fn scaling_value(
+        &self,
+        output: randr::Output,
+        name: &str,
+        scaling: Scaling,
+    ) -> Result<Atom, DisplayError> {
+        let value_name: &[u8] = match scaling {
+            Scaling::Stretched => b"Full",
+            Scaling::Centered => b"Center",
+            Scaling::AspectPreserving => b"Full aspect",
+        };
+        let value = self
+            .conn
+            .intern_atom(true, value_name)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?
+            .atom;
+        let valid = self
+            .conn
+            .randr_query_output_property(output, self.scaling_atom)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map(|info| info.valid_values)
+            .unwrap_or_default();
+
+        if value == x11rb::NONE || !valid.contains(&(value as i32)) {
+            return Err(DisplayError::scaling_not_supported(name, scaling));
+        }
+        Ok(value)
+    }
+
+    fn set_scaling(&self, output: randr::Output, value: Atom) -> Result<(), String> {
+        self.conn
+            .randr_change_output_property(
+                output,
+                self.scaling_atom,
+                AtomEnum::ATOM.into(),
+                32,
+                PropMode::REPLACE,
+                1,
+                &value.to_ne_bytes(),
+            )
+            .map_err(|e| e.to_string())?
+            .check()
+            .map_err(|e| e.to_string())
+    }
+
+    fn find_mode(&self, state: &OutputState, res: &Resolution) -> Option<ModeInfo> {
+        state
+            .modes
+            .iter()
+            .find(|m| to_resolution(m).same_mode(res))
+            .copied()
+    }
+
+    fn crtc_info(&self, state: &OutputState) -> Result<randr::GetCrtcInfoReply, String> {
+        self.conn
+            .randr_get_crtc_info(state.crtc, state.config_timestamp)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Smallest screen that still contains every active CRTC once `target` is
+    /// resized to `size`.
+    fn required_screen_size(
+        &self,
+        state: &OutputState,
+        target: randr::Crtc,
+        size: (u16, u16),
+    ) -> Result<(u16, u16), String> {
+        let mut width = 0u16;
+        let mut height = 0u16;
+
+        for crtc in &state.crtcs {
+            let info = self
+                .conn
+                .randr_get_crtc_info(*crtc, state.config_timestamp)
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?;
+            if info.mode == x11rb::NONE {
+                continue;
+            }
+
+            let (w, h) = if *crtc == target {
+                size
+            } else {
+                (info.width, info.height)
+            };
+            width = width.max((info.x.max(0) as u16).saturating_add(w));
+            height = height.max((info.y.max(0) as u16).saturating_add(h));
+        }
+
+        Ok((width, height))
+    }
+
+    fn output_info(
+        &self,
+        output: randr::Output,
+        timestamp: u32,
+    ) -> Result<randr::GetOutputInfoReply, String> {
+        self.conn
+            .randr_get_output_info(output, timestamp)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())
+    }
+
+    fn crtc_info_at(
+        &self,
+        crtc: randr::Crtc,
+        timestamp: u32,
+    ) -> Result<randr::GetCrtcInfoReply, String> {
+        self.conn
+            .randr_get_crtc_info(crtc, timestamp)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())
+    }
+
+    /// Matches every layout to its output, mode and a CRTC to drive it. Outputs
+    /// being enabled get a CRTC nobody else uses.
+    fn plan_topology<'a>(
+        &self,
+        resources: &randr::GetScreenResourcesCurrentReply,
+        topology: &'a Topology,
+    ) -> Result<Vec<PlannedOutput<'a>>, DisplayError> {
+        let timestamp = resources.config_timestamp;
+        let mut outputs = Vec::new();
+        for output in &resources.outputs {
+            outputs.push((*output, self.output_info(*output, timestamp)?));
+        }
+
+        // CRTCs that stay busy: driving an output outside the plan.
+        let mut taken: Vec<randr::Crtc> = outputs
+            .iter()
+            .filter(|(_, info)| {
+                info.crtc != x11rb::NONE
+                    && !topology
+                        .outputs
+                        .iter()
+                        .any(|l| info.name == l.display.device_name.as_bytes())
+            })
+            .map(|(_, info)| info.crtc)
+            .collect();
+
+        let mut plan = Vec::new();
+        for layout in &topology.outputs {
+            let name = &layout.display.device_name;
+            let Some((output, info)) = outputs.iter().find(|(_, i)| i.name == name.as_bytes())
+            else {
+                return Err(DisplayError::device_not_found(name));
+            };
+
+            let mode = if layout.enabled {
+                let mode = info
+                    .modes
+                    .iter()
+                    .filter_map(|id| resources.modes.iter().find(|m| m.id == *id))
+                    .find(|m| to_resolution(m).same_mode(&layout.mode))
+                    .copied()
+                    .ok_or_else(|| DisplayError::mode_not_supported(&layout.mode, Some(name)))?;
+                Some(mode)
+            } else {
+                None
+            };
+
+            let scaling = match layout.mode.scaling.filter(|_| layout.enabled) {
+                Some(scaling) => Some(self.scaling_value(*output, name, scaling)?),
+                None => None,
+            };
+
+            plan.push(PlannedOutput {
+                output: *output,
+                layout,
+                current_crtc: info.crtc,
+                crtc: info.crtc,
+                mode,
+                scaling,
+            });
+        }
+
+        // Keep enabled outputs on their CRTC; find one for outputs being turned on.
+        for planned in plan.iter_mut() {
+            if planned.mode.is_none() {
+                planned.crtc = x11rb::NONE;
+            } else if planned.crtc != x11rb::NONE {
+                taken.push(planned.crtc);
+            }
+        }
+        for planned in plan.iter_mut() {
+            if planned.mode.is_none() || planned.crtc != x11rb::NONE {
+                continue;
+            }
+            let info = &outputs
+                .iter()
+                .find(|(o, _)| *o == planned.output)
+                .ok_or_else(|| "Output vanished while planning".to_string())?
+                .1;
+            let crtc = info
+                .crtcs
+                .iter()
+                .copied()
+                .find(|c| !taken.contains(c))
+                .ok_or_else(|| DisplayError::BadParams {
+                    message: format!("No free CRTC for {}", planned.layout.display.device_name),
+                })?;
+            taken.push(crtc);
+            planned.crtc = crtc;
+        }
+
+        for planned in &plan {
+            if planned.mode.is_none() {
+                continue;
+            }
+            let crtc = self.crtc_info_at(planned.crtc, timestamp)?;
+            let rotation = to_randr_rotation(planned.layout.rotation);
+            if !crtc.rotations.contains(rotation) {
+                return Err(DisplayError::rotation_not_supported(
+                    &planned.layout.display.device_name,
+                    planned.layout.rotation,
+                ));
+            }
+        }
+
+        Ok(plan)
+    }
+
+    fn apply_plan(
+        &self,
+        plan: &[PlannedOutput],
+        timestamp: u32,
+        (width, height): (u16, u16),
+    ) -> Result<(), String> {
+        let set_crtc = |crtc, x, y, mode, rotation, outputs: &[randr::Output]| {
+            let reply = self
+                .conn
+                .randr_set_crtc_config(crtc, CURRENT_TIME, timestamp, x, y, mode, rotation, outputs)
+                .map_err(|e| e.to_string())?
+                .reply()
+                .map_err(|e| e.to_string())?;
+            if reply.status == SetConfig::SUCCESS {
+                Ok(())
+            } else {
+                Err(format!(
+                    "RRSetCrtcConfig failed with status: {:?}",
+                    reply.status
+                ))
+            }
+        };
+
+        // Free every CRTC that is being turned off or moved, and any that would
+        // hang off the new screen, before resizing it.
+        for planned in plan {
+            if planned.current_crtc == x11rb::NONE {
+                continue;
+            }
+            let current = self.crtc_info_at(planned.current_crtc, timestamp)?;
+            let fits = (current.x.max(0) as u16).saturating_add(current.width) <= width
+                && (current.y.max(0) as u16).saturating_add(current.height) <= height;
+            if planned.mode.is_none() || planned.crtc != planned.current_crtc || !fits {
+                set_crtc(
+                    planned.current_crtc,
+                    0,
+                    0,
+                    x11rb::NONE,
+                    randr::Rotation::ROTATE0,
+                    &[],
+                )?;
+            }
+        }
+
+        self.set_screen_size(width, height)?;
+
+        for planned in plan {
+            let Some(mode) = &planned.mode else {
+                continue;
+            };
+            // Property changes take effect with the next mode set.
+            if let Some(scaling) = planned.scaling {
+                self.set_scaling(planned.output, scaling)?;
+            }
+            set_crtc(
+                planned.crtc,
+                planned.layout.x as i16,
+                planned.layout.y as i16,
+                mode.id,
+                to_randr_rotation(planned.layout.rotation),
+                &[planned.output],
+            )?;
+        }
+
+        if let Some(primary) = plan.iter().find(|p| p.mode.is_some() && p.layout.primary) {
+            self.conn
+                .randr_set_output_primary(self.root, primary.output)
+                .map_err(|e| e.to_string())?
+                .check()
+                .map_err(|e| e.to_string())?;
+        }
+
+        Ok(())
+    }
+
+    fn set_screen_size(&self, width: u16, height: u16) -> Result<(), String> {
+        let mm_width = (width as f64 * self.mm_per_px.0).round() as u32;
+        let mm_height = (height as f64 * self.mm_per_px.1).round() as u32;
+        self.conn
+            .randr_set_screen_size(self.root, width, height, mm_width, mm_height)
+            .map_err(|e| e.to_string())?
+            .check()
+            .map_err(|e| e.to_string())
+    }
+}
+
+impl DisplayBackend for XrandrBackend {
+    fn name(&self) -> &'static str {
+        "xrandr"
+    }
+
+    fn displays(&self) -> Vec<DisplayInfo> {
+        let outputs = match self.resources().and_then(|r| self.active_outputs(&r)) {
+            Ok(outputs) => outputs,
+            Err(e) => {
+                log::error!("Failed to list RandR outputs: {}", e);
+                return Vec::new();
+            }
+        };
+        let primary = self.primary_output().unwrap_or(x11rb::NONE);
+
+        outputs
+            .into_iter()
+            .map(|(output, info)| self.describe(output, &info, primary))
+            .collect()
+    }
+
+    fn enumerate_modes(&self, device: Option<&str>) -> Vec<Resolution> {
+        match self.output_state(device) {
+            Ok(state) => state.modes.iter().map(to_resolution).collect(),
+            Err(e) => {
+                log::error!("Failed to enumerate RandR modes: {}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    fn current_mode(&self, device: Option<&str>) -> Option<Resolution> {
+        let state = self.output_state(device).ok()?;
+        let crtc = self.crtc_info(&state).ok()?;
+        state
+            .modes
+            .iter()
+            .find(|m| m.id == crtc.mode)
+            .map(|mode| Resolution {
+                scaling: self.scaling(state.output),
+                rotation: Some(from_randr_rotation(crtc.rotation)),
+                ..to_resolution(mode)
+            })
+    }
+
+    fn apply_mode(
+        &self,
+        device: Option<&str>,
+        res: &Resolution,
+        persistence: Persistence,
+    ) -> Result<(), DisplayError> {
+        if persistence != Persistence::Temporary {
+            log::warn!(
+                "{} cannot store display modes, applying for this session only",
+                self.name()
+            );
+        }
+        let state = self.output_state(device)?;
+        let Some(mode) = self.find_mode(&state, res) else {
+            return Err(DisplayError::mode_not_supported(res, Some(&state.name)));
+        };
+
+        log::info!(
+            "Applying {}x{}@{} to RandR output {}",
+            res.width,
+            res.height,
+            res.frequency,
+            state.name
+        );
+
+        // Property changes take effect with the mode set below.
+        if let Some(scaling) = res.scaling {
+            let value = self.scaling_value(state.output, &state.name, scaling)?;
+            self.set_scaling(state.output, value)?;
+        }
+
+        let crtc = self.crtc_info(&state)?;
+        let rotation = target_rotation(&crtc, res, &state.name)?;
+        let size = crtc_size(&mode, rotation);
+
+        let current_screen = self
+            .conn
+            .get_geometry(self.root)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?;
+        let (width, height) = self.required_screen_size(&state, state.crtc, size)?;
+
+        // Grow the screen before the CRTC needs the space, shrink it afterwards.
+        let grow = width > current_screen.width || height > current_screen.height;
+        if grow {
+            self.set_screen_size(
+                width.max(current_screen.width),
+                height.max(current_screen.height),
+            )?;
+        }
+
+        let reply = self
+            .conn
+            .randr_set_crtc_config(
+                state.crtc,
+                CURRENT_TIME,
+                state.config_timestamp,
+                crtc.x,
+                crtc.y,
+                mode.id,
+                rotation,
+                &[state.output],
+            )
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?;
+
+        if reply.status != SetConfig::SUCCESS {
+            return Err(format!("RRSetCrtcConfig failed with status: {:?}", reply.status).into());
+        }
+
+        if width != current_screen.width || height != current_screen.height {
+            self.set_screen_size(width, height)?;
+        }
+
+        self.conn.flush().map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    fn test_mode(&self, device: Option<&str>, res: &Resolution) -> Result<(), DisplayError> {
+        // RandR has no dry-run, so check the mode exists and fits the screen limits.
+        let state = self.output_state(device)?;
+        let Some(mode) = self.find_mode(&state, res) else {
+            return Err(DisplayError::mode_not_supported(res, Some(&state.name)));
+        };
+        if let Some(scaling) = res.scaling {
+            self.scaling_value(state.output, &state.name, scaling)?;
+        }
+
+        let crtc = self.crtc_info(&state)?;
+        let rotation = target_rotation(&crtc, res, &state.name)?;
+        let range = self
+            .conn
+            .randr_get_screen_size_range(self.root)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?;
+        let (width, height) =
+            self.required_screen_size(&state, state.crtc, crtc_size(&mode, rotation))?;
+
+        if width > range.max_width || height > range.max_height {
+            return Err(DisplayError::BadParams {
+                message: format!(
+                    "Screen would be {}x{}, larger than the maximum {}x{}",
+                    width, height, range.max_width, range.max_height
+                ),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn topology(&self) -> Result<Topology, DisplayError> {
+        let resources = self.resources()?;
+        let primary = self.primary_output()?;
+        let mut outputs = Vec::new();
+
+        for output in &resources.outputs {
+            let info = self.output_info(*output, resources.config_timestamp)?;
+            if info.connection != randr::Connection::CONNECTED {
+                continue;
+            }
+            let display = self.describe(*output, &info, primary).target();
+            let mode_of = |id: u32| resources.modes.iter().find(|m| m.id == id);
+
+            let layout = if info.crtc != x11rb::NONE {
+                let crtc = self.crtc_info_at(info.crtc, resources.config_timestamp)?;
+                OutputLayout {
+                    display,
+                    enabled: true,
+                    primary: *output == primary,
+                    mode: mode_of(crtc.mode).map(to_resolution).unwrap_or_default(),
+                    x: crtc.x as i32,
+                    y: crtc.y as i32,
+                    rotation: from_randr_rotation(crtc.rotation),
+                }
+            } else {
+                // Disabled outputs have no mode of their own; offer the preferred one.
+                let preferred = info.modes.first().and_then(|id| mode_of(*id));
+                OutputLayout {
+                    display,
+                    enabled: false,
+                    primary: false,
+                    mode: preferred.map(to_resolution).unwrap_or_default(),
+                    x: 0,
+                    y: 0,
+                    rotation: Rotation::Normal,
+                }
+            };
+            outputs.push(layout);
+        }
+
+        Ok(Topology { outputs })
+    }
+
+    fn apply_topology(
+        &self,
+        topology: &Topology,
+        persistence: Persistence,
+    ) -> Result<(), DisplayError> {
+        if persistence != Persistence::Temporary {
+            log::warn!(
+                "{} cannot store display layouts, applying for this session only",
+                self.name()
+            );
+        }
+        let resources = self.resources()?;
+        let timestamp = resources.config_timestamp;
+        let plan = self.plan_topology(&resources, topology)?;
+
+        // Everything outside the plan keeps its CRTC, so it bounds the screen too.
+        let mut width = 0u16;
+        let mut height = 0u16;
+        let planned_crtcs: Vec<randr::Crtc> = plan.iter().map(|p| p.current_crtc).collect();
+        for crtc in &resources.crtcs {
+            if planned_crtcs.contains(crtc) {
+                continue;
+            }
+            let info = self.crtc_info_at(*crtc, timestamp)?;
+            if info.mode != x11rb::NONE {
+                width = width.max((info.x.max(0) as u16).saturating_add(info.width));
+                height = height.max((info.y.max(0) as u16).saturating_add(info.height));
+            }
+        }
+        for planned in &plan {
+            if let Some(mode) = &planned.mode {
+                let (w, h) = crtc_size(mode, to_randr_rotation(planned.layout.rotation));
+                width = width.max((planned.layout.x.max(0) as u16).saturating_add(w));
+                height = height.max((planned.layout.y.max(0) as u16).saturating_add(h));
+            }
+        }
+
+        let range = self
+            .conn
+            .randr_get_screen_size_range(self.root)
+            .map_err(|e| e.to_string())?
+            .reply()
+            .map_err(|e| e.to_string())?;
+        if width > range.max_width || height > range.max_height {
+            return Err(DisplayError::BadParams {
+                message: format!(
+                    "Screen would be {}x{}, larger than the maximum {}x{}",
+                    width, height, range.max_width, range.max_height
+                ),
+            });
+        }
+        let (width, height) = (width.max(range.min_width), height.max(range.min_height));
+
+        log::info!(
+            "Applying RandR topology with {} output(s), screen {}x{}",
+            plan.len(),
+            width,
+            height
+        );
+
+        // Hold the server so clients never see the intermediate layouts.
+        self.conn
+            .grab_server()
+            .map_err(|e| e.to_string())?
+            .check()
+            .map_err(|e| e.to_string())?;
+        let result = self.apply_plan(&plan, timestamp, (width, height));
+        let _ = self.conn.ungrab_server();
+        self.conn.flush().map_err(|e| e.to_string())?;
+        Ok(result?)
+    }
+}
+
+/// Rotation to drive `crtc` with for `res`: the requested one (keeping any
+/// reflection) or the current one when `res` leaves it open.
+fn target_rotation(
+    crtc: &randr::GetCrtcInfoReply,
+    res: &Resolution,
+    name: &str,
+) -> Result<randr::Rotation, DisplayError> {
+    let Some(rotation) = res.rotation else {
+        return Ok(crtc.rotation);
+    };
+    let wanted = to_randr_rotation(rotation);
+    if !crtc.rotations.contains(wanted) {
+        return Err(DisplayError::rotation_not_supported(name, rotation));
+    }
+    let reflection = randr::Rotation::REFLECT_X | randr::Rotation::REFLECT_Y;
+    Ok(wanted | (crtc.rotation & reflection))
+}
+
+/// RandR counts rotations counter-clockwise, `Rotation` clockwise.
+fn to_randr_rotation(rotation: Rotation) -> randr::Rotation {
+    match rotation {
+        Rotation::Normal => randr::Rotation::ROTATE0,
+        Rotation::Rotate90 => randr::Rotation::ROTATE270,
+        Rotation::Rotate180 => randr::Rotation::ROTATE180,
+        Rotation::Rotate270 => randr::Rotation::ROTATE90,
+    }
+}
+
+fn from_randr_rotation(rotation: randr::Rotation) -> Rotation {
+    if rotation.contains(randr::Rotation::ROTATE90) {
+        Rotation::Rotate270
+    } else if rotation.contains(randr::Rotation::ROTATE180) {
+        Rotation::Rotate180
+    } else if rotation.contains(randr::Rotation::ROTATE270) {
+        Rotation::Rotate90
+    } else {
+        Rotation::Normal
+    }
+}
+
+/// Refresh rate of a mode line, rounded to whole Hz like the other backends report it.
+fn refresh_rate(mode: &ModeInfo) -> u32 {
+    let mut vtotal = mode.vtotal as f64;
+    if mode.mode_flags.contains(ModeFlag::DOUBLE_SCAN) {
+        vtotal *= 2.0;
+    }
+    if mode.mode_flags.contains(ModeFlag::INTERLACE) {
+        vtotal /= 2.0;
+    }
+
+    let pixels = mode.htotal as f64 * vtotal;
+    if pixels == 0.0 {
+        return 0;
+    }
+    (mode.dot_clock as f64 / pixels).round() as u32
+}
+
+/// Size the mode occupies on the screen; sideways rotations swap the axes.
+fn crtc_size(mode: &ModeInfo, rotation: randr::Rotation) -> (u16, u16) {
+    if rotation.intersects(randr::Rotation::ROTATE90 | randr::Rotation::ROTATE270) {
+        (mode.height, mode.width)
+    } else {
+        (mode.width, mode.height)
+    }
+}
+
+fn to_resolution(mode: &ModeInfo) -> Resolution {
+    Resolution {
+        interlaced: mode.mode_flags.contains(ModeFlag::INTERLACE),
+        ..Resolution::new(mode.width as u32, mode.height as u32, refresh_rate(mode))
+    }
+}
@@ -0,0 +1,21 @@
+#[cfg(target_os = "linux")]
+mod unix;
+#[cfg(target_os = "windows")]
+mod win32;
+
+pub type EndHandler = Box<dyn Fn() + Send>;
+
+/// Calls `on_end` when the OS ends the session or asks the process to stop:
+/// `WM_ENDSESSION` (logoff, shutdown) on Windows, SIGTERM/SIGINT on Linux.
+/// The handler runs on a background thread and must finish the cleanup before
+/// returning, since the process may be killed right after.
+pub fn watch(on_end: EndHandler) {
+    #[cfg(target_os = "windows")]
+    win32::watch(on_end);
+
+    #[cfg(target_os = "linux")]
+    unix::watch(on_end);
+
+    #[cfg(not(any(target_os = "windows", target_os = "linux")))]
+    drop(on_end);
+}
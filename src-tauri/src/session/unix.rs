@@ -0,0 +1,22 @@
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+use super::EndHandler;
+
+pub fn watch(on_end: EndHandler) {
+    let mut signals = match Signals::new([SIGTERM, SIGINT]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            log::warn!("Cannot listen for termination signals: {}", e);
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        println!("Starting Signal Watcher Thread...");
+        if let Some(signal) = signals.forever().next() {
+            log::info!("Received signal {}, shutting down", signal);
+            on_end();
+        }
+    });
+}
@@ -0,0 +1,51 @@
+use std::cell::RefCell;
+
+use windows::core::w;
+use windows::Win32::{
+    Foundation::{HWND, LPARAM, LRESULT, WPARAM},
+    UI::WindowsAndMessaging::{DefWindowProcW, WM_ENDSESSION, WM_QUERYENDSESSION},
+};
+
+use super::EndHandler;
+use crate::hidden_window;
+
+thread_local! {
+    // The window procedure runs on the thread that created the window.
+    static HANDLER: RefCell<Option<EndHandler>> = const { RefCell::new(None) };
+}
+
+unsafe extern "system" fn window_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    match msg {
+        // Never veto a logoff; the cleanup waits for WM_ENDSESSION so nothing
+        // is reverted if another application cancels it.
+        WM_QUERYENDSESSION => LRESULT(1),
+        WM_ENDSESSION => {
+            if wparam.0 != 0 {
+                log::info!("Session is ending, shutting down");
+                HANDLER.with(|handler| {
+                    if let Some(handler) = handler.borrow_mut().take() {
+                        handler();
+                    }
+                });
+            }
+            LRESULT(0)
+        }
+        _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+    }
+}
+
+/// Session-end messages are only broadcast to top-level windows, so they are
+/// received by a hidden one on its own thread.
+pub fn watch(on_end: EndHandler) {
+    std::thread::spawn(move || {
+        println!("Starting Session Watcher Thread...");
+        HANDLER.with(|h| *h.borrow_mut() = Some(on_end));
+
+        hidden_window::run(w!("KortexSessionWatcher"), window_proc, "session end");
+    });
+}
@@ -2,26 +2,139 @@ import { invoke } from '@tauri-apps/api/core';
 
 export type Theme = "dark" | "light" | "system";
 
-export interface Resolution {
+export interface MonitorId {
+    manufacturer: string;
+    product: string;
+    serial?: string;
+}
+
+export interface DisplayTarget {
+    deviceName: string;
+    connector?: string;
+    monitor?: MonitorId;
+}
+
+export interface DisplayInfo {
+    deviceName: string;
+    connector: string;
+    monitor: MonitorId | null;
+    name: string;
+    primary: boolean;
+}
+
+export type Scaling = "stretched" | "centered" | "aspectPreserving";
+
+// Optional parts of a mode; unset means "whatever the driver pairs with it".
+// With a rotation, width/height stay in the panel's native orientation.
+export interface ModeFormat {
+    bitsPerPel?: number;
+    scaling?: Scaling;
+    interlaced?: boolean;
+    rotation?: Rotation;
+}
+
+export interface Resolution extends ModeFormat {
     width: number;
     height: number;
     frequency: number;
+    display?: DisplayTarget;
+}
+
+export type Rotation = "normal" | "rotate90" | "rotate180" | "rotate270";
+
+export interface OutputLayout {
+    display: DisplayTarget;
+    enabled: boolean;
+    primary: boolean;
+    mode: Resolution;
+    x: number;
+    y: number;
+    rotation: Rotation;
+}
+
+export interface Topology {
+    outputs: OutputLayout[];
 }
 
 export interface ResolutionProfile extends Resolution {
     processName: string;
 }
 
+// Whether a mode change also becomes the stored desktop mode (user or all users).
+export type Persistence = 'temporary' | 'updateRegistry' | 'global';
+
+// What to apply when the display lacks the profile's exact mode.
+export type FallbackPolicy = 'exact' | 'sameResolution' | 'closestAspect' | 'closestPixels';
+
+// When a profile applies: while its process has focus, while it runs, or only when launched after Kortex.
+export type Trigger = 'whileFocused' | 'whileRunning' | 'onLaunch';
+
+// How to treat a mode change made by someone else while a profile is active.
+export type ExternalChangePolicy = 'keepOriginal' | 'updateOriginal' | 'forgetProfile';
+
+// Payload of the `display-changed` event.
+export interface DisplayChangedEvent {
+    external: boolean;
+    resolution?: string;
+    policy: ExternalChangePolicy;
+}
+
+// Payload of the `display-hotplug` event; `process` is set when its profile was re-applied.
+export interface DisplayHotplugEvent {
+    added: string[];
+    removed: string[];
+    process?: string;
+    resolution?: string;
+    error?: DisplayError;
+}
+
+// How a rule picks out a process; rules without one match `process` by name.
+export type ProcessMatcher =
+    | { kind: 'name'; name: string }
+    | { kind: 'path'; path: string }
+    | { kind: 'pathPrefix'; path: string }
+    | { kind: 'glob'; pattern: string }
+    | { kind: 'regex'; pattern: string };
+
+// Substring (ignoring case) or regex test on a window title or class.
+export type TextMatcher =
+    | { kind: 'contains'; text: string }
+    | { kind: 'regex'; pattern: string };
+
+// Extra conditions on top of the process matcher; all set ones must hold.
+export interface MatchConditions {
+    windowTitle?: TextMatcher;
+    windowClass?: TextMatcher;
+    // Arguments after the executable, joined with spaces.
+    commandLine?: TextMatcher;
+    // Any process up the parent chain, e.g. steam.exe.
+    ancestor?: ProcessMatcher;
+    // Window state: covers its whole monitor, has no frame, is at least this large.
+    fullscreen?: boolean;
+    borderless?: boolean;
+    minWidth?: number;
+    minHeight?: number;
+}
+
 // Matches the JSON from backend
-export interface ProfileConfig {
+export interface ProfileConfig extends ModeFormat, MatchConditions {
     process: string;
+    matcher?: ProcessMatcher;
+    // Higher wins when several profiles match (default 0).
+    priority?: number;
+    trigger?: Trigger;
     width: number;
     height: number;
     frequency: number;
+    display?: DisplayTarget;
+    fallback?: FallbackPolicy;
+    persistence?: Persistence;
+    topology?: Topology;
 }
 
-export interface MouseLockItem {
+export interface MouseLockItem extends MatchConditions {
     process: string;
+    matcher?: ProcessMatcher;
     paddingX: number;
     paddingY: number;
 }
@@ -32,6 +145,7 @@ export interface AutomationSettings {
         revertDelay: number;
         defaultProfile?: Resolution;
         profiles: ProfileConfig[];
+        externalChange?: ExternalChangePolicy;
     };
 }
 
@@ -64,10 +178,73 @@ export async function getConfig(): Promise<Config> {
     return await invoke('get_config');
 }
 
-export async function saveConfig(config: Config): Promise<void> {
+// Rejection reasons from display commands (thrown by setResolution & co.).
+export type DisplayError =
+    | { kind: 'modeNotSupported'; mode: string; device: string | null }
+    | { kind: 'restartRequired' }
+    | { kind: 'badFlags' }
+    | { kind: 'badParams'; message: string }
+    | { kind: 'deviceNotFound'; device: string }
+    | { kind: 'backendFailure'; message: string };
+
+export function describeDisplayError(error: DisplayError): string {
+    switch (error.kind) {
+        case 'modeNotSupported':
+            return `${error.mode} is not supported by ${error.device ?? 'the display'}. Pick another mode.`;
+        case 'restartRequired':
+            return 'The change will apply after restarting the computer.';
+        case 'badFlags':
+            return 'The display driver rejected the request.';
+        case 'badParams':
+            return `Invalid display settings: ${error.message}`;
+        case 'deviceNotFound':
+            return `Display not connected: ${error.device}. Reconnect it or pick another display.`;
+        case 'backendFailure':
+            return `Display change failed: ${error.message}`;
+    }
+}
+
+// Problems found while saving; the config is stored regardless. `index` is the
+// position in `profiles` (null for the default profile).
+export type ProfileIssue = { process: string | null; index: number | null } & (
+    | { problem: 'rejected'; error: DisplayError }
+    | { problem: 'duplicate'; of: number }
+    | { problem: 'shadowed'; by: number }
+);
+
+export interface RuleRank {
+    priority: number;
+    specificity: number;
+    conditions: number;
+}
+
+export interface RuleVerdict {
+    index: number;
+    process: string;
+    matcher: string;
+    matched: boolean;
+    // Fullscreen/borderless/size conditions were not checked (no window to measure)
+    windowUnchecked: boolean;
+    rank: RuleRank;
+}
+
+export interface MatchExplanation {
+    name: string;
+    path: string | null;
+    rules: RuleVerdict[];
+    winner: number | null;
+    reason: string;
+}
+
+export async function saveConfig(config: Config): Promise<ProfileIssue[]> {
     return await invoke('save_config', { config });
 }
 
+// Which profile wins for a running process (optionally with a window title/class) and why.
+export async function explainMatch(pid: number, title?: string, windowClass?: string): Promise<MatchExplanation> {
+    return await invoke('explain_match', { pid, title, class: windowClass });
+}
+
 export async function getProfilesList(): Promise<ResolutionProfile[]> {
     const config = await getConfig();
     // Map ProfileConfig (backend) to ResolutionProfile (frontend)
@@ -76,13 +253,15 @@ export async function getProfilesList(): Promise<ResolutionProfile[]> {
         processName: p.process,
         width: p.width,
         height: p.height,
-        frequency: p.frequency
+        frequency: p.frequency,
+        display: p.display
     }));
 }
 
 export interface ProcessInfo {
     pid: number;
     name: string;
+    path: string | null;
     memory: number;
 }
 
@@ -90,16 +269,46 @@ export async function fetchProcesses(): Promise<ProcessInfo[]> {
     return await invoke('fetch_processes');
 }
 
-export async function getSupportedResolutions(): Promise<Resolution[]> {
-    return await invoke('get_resolutions');
+// Running processes the rule would match; rejects with a message for a bad pattern.
+export async function testMatcher(matcher: ProcessMatcher): Promise<ProcessInfo[]> {
+    return await invoke('test_matcher', { matcher });
+}
+
+export async function getDisplays(): Promise<DisplayInfo[]> {
+    return await invoke('get_displays');
+}
+
+export async function getSupportedResolutions(display?: string): Promise<Resolution[]> {
+    return await invoke('get_resolutions', { display });
+}
+
+export async function getCurrentResolution(display?: string): Promise<Resolution | null> {
+    return await invoke('get_current_res', { display });
+}
+
+// Rolls back after `timeout` seconds (15 by default) unless confirmResolution() is called.
+export async function setResolution(width: number, height: number, frequency: number, display?: string, format: ModeFormat = {}, persistence?: Persistence, timeout?: number): Promise<void> {
+    return await invoke('set_resolution', { mode: { width, height, frequency, ...format }, display, persistence, timeout });
+}
+
+export async function confirmResolution(): Promise<boolean> {
+    return await invoke('confirm_resolution');
+}
+
+export async function revertResolution(): Promise<boolean> {
+    return await invoke('revert_resolution');
+}
+
+export async function testResolution(width: number, height: number, frequency: number, display?: string, format: ModeFormat = {}): Promise<void> {
+    return await invoke('test_resolution', { mode: { width, height, frequency, ...format }, display });
 }
 
-export async function getCurrentResolution(): Promise<Resolution | null> {
-    return await invoke('get_current_res');
+export async function getTopology(): Promise<Topology> {
+    return await invoke('get_topology');
 }
 
-export async function setResolution(width: number, height: number, frequency: number): Promise<void> {
-    return await invoke('set_resolution', { width, height, frequency });
+export async function setTopology(topology: Topology, persistence?: Persistence): Promise<void> {
+    return await invoke('set_topology', { topology, persistence });
 }
 
 export async function openConfigFolder() {
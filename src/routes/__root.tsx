@@ -8,7 +8,15 @@ import { useEffect, useState } from 'react'
 import { listen } from '@tauri-apps/api/event'
 import { Tooltip, TooltipContent, TooltipTrigger } from '../components/ui/tooltip'
 import { Popover, PopoverContent, PopoverTrigger } from '../components/ui/popover'
-import { forceRevert } from '../lib/store'
+import {
+    AlertDialog,
+    AlertDialogContent,
+    AlertDialogDescription,
+    AlertDialogFooter,
+    AlertDialogHeader,
+    AlertDialogTitle,
+} from '../components/ui/alert-dialog'
+import { confirmResolution, forceRevert, revertResolution } from '../lib/store'
 
 export const Route = createRootRoute({
     component: RootComponent,
@@ -22,6 +30,8 @@ function ResolutionIndicator() {
     useEffect(() => {
         const unlisten = listen('resolution-changed', (event: any) => {
             console.log("Resolution event:", event.payload)
+            // Manual changes waiting for confirmation have their own dialog
+            if (['confirm-pending', 'confirmed', 'rolled-back'].includes(event.payload.status)) return
             setStatus(event.payload)
             setShowIndicator(true)
 
@@ -75,6 +85,70 @@ function ResolutionIndicator() {
     )
 }
 
+// "Keep these display settings?" prompt for manual changes; they roll back
+// on their own unless kept before the countdown runs out.
+function ConfirmChangeDialog() {
+    const [pending, setPending] = useState<{ resolution?: string, deadline: number } | null>(null)
+    const [now, setNow] = useState(Date.now())
+
+    useEffect(() => {
+        const unlisten = listen('resolution-changed', (event: any) => {
+            const { status, resolution, timeout, process } = event.payload
+            if (status === 'confirm-pending') {
+                setPending({ resolution, deadline: Date.now() + timeout * 1000 })
+                setNow(Date.now())
+            } else if (status === 'confirmed' || status === 'rolled-back' || (status === 'failed' && !process)) {
+                setPending(null)
+            }
+        })
+
+        return () => {
+            unlisten.then(f => f())
+        }
+    }, [])
+
+    useEffect(() => {
+        if (!pending) return
+        const timer = setInterval(() => setNow(Date.now()), 250)
+        return () => clearInterval(timer)
+    }, [pending])
+
+    const secondsLeft = pending ? Math.max(0, Math.ceil((pending.deadline - now) / 1000)) : 0
+
+    return (
+        <AlertDialog open={pending !== null}>
+            <AlertDialogContent>
+                <AlertDialogHeader>
+                    <AlertDialogTitle>Keep these display settings?</AlertDialogTitle>
+                    <AlertDialogDescription>
+                        {pending?.resolution && <>Switched to {pending.resolution}. </>}
+                        Reverting in {secondsLeft}s.
+                    </AlertDialogDescription>
+                </AlertDialogHeader>
+                <AlertDialogFooter>
+                    <Button
+                        variant="outline"
+                        onClick={() => {
+                            revertResolution().catch(e => console.error("Failed to revert resolution", e))
+                            setPending(null)
+                        }}
+                    >
+                        Revert
+                    </Button>
+                    <Button
+                        onClick={() => {
+                            confirmResolution().catch(e => console.error("Failed to confirm resolution", e))
+                            setPending(null)
+                        }}
+                    >
+                        Keep Changes
+                    </Button>
+                </AlertDialogFooter>
+            </AlertDialogContent>
+        </AlertDialog>
+    )
+}
+
 function MouseLockIndicator() {
     const [status, setStatus] = useState<{ process?: string, status: string } | null>(null)
     const [visible, setVisible] = useState(false)
@@ -162,6 +236,8 @@ function RootComponent() {
                     </div>
                 </header>
 
+                <ConfirmChangeDialog />
+
                 <main className="flex-1 overflow-hidden">
                     <ScrollArea className="h-full">
                         <div className="p-4">
@@ -1,5 +1,6 @@
 import { createFileRoute } from '@tanstack/react-router'
 import { useState, useEffect } from 'react'
+import { listen } from '@tauri-apps/api/event'
 import { getSupportedResolutions, getCurrentResolution, setResolution, type Resolution } from '../lib/store'
 import { Button } from '../components/ui/button'
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from '../components/ui/card'
@@ -19,6 +20,14 @@ function ManualTab() {
 
     useEffect(() => {
         loadData()
+
+        // An unconfirmed change puts the previous mode back
+        const unlisten = listen('resolution-changed', (event: any) => {
+            if (event.payload.status === 'rolled-back') loadData()
+        })
+        return () => {
+            unlisten.then(f => f())
+        }
     }, [])
 
     const loadData = async () => {
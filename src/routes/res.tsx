@@ -1,6 +1,6 @@
 import { createFileRoute } from '@tanstack/react-router'
 import { useEffect, useState } from 'react'
-import { getConfig, saveConfig, fetchProcesses, getSupportedResolutions, type ResolutionProfile, type ProcessInfo, type Resolution } from '../lib/store'
+import { getConfig, saveConfig, fetchProcesses, getSupportedResolutions, type Config, type ResolutionProfile, type ProcessInfo, type Resolution } from '../lib/store'
 import { Button } from '../components/ui/button'
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from '../components/ui/card'
 // import { Input } from '../components/ui/input'
@@ -32,14 +32,20 @@ export const Route = createFileRoute('/res')({
     component: ProfilesTab,
 })
 
-function SortableProfileItem({ profile, onDelete }: { profile: ResolutionProfile, onDelete: (name: string) => void }) {
+// A profile as listed here; `index` is its position in the saved config, which
+// keeps the fields this page does not edit reachable.
+type ProfileRow = ResolutionProfile & { index: number }
+
+const rowId = (profile: ProfileRow) => `profile-${profile.index}`
+
+function SortableProfileItem({ profile, onDelete }: { profile: ProfileRow, onDelete: (index: number) => void }) {
     const {
         attributes,
         listeners,
         setNodeRef,
         transform,
         transition,
-    } = useSortable({ id: profile.processName })
+    } = useSortable({ id: rowId(profile) })
 
     const style = {
         transform: CSS.Transform.toString(transform),
@@ -64,7 +70,7 @@ function SortableProfileItem({ profile, onDelete }: { profile: ResolutionProfile
                 <div className="text-sm text-muted-foreground">
                     {profile.width} × {profile.height} @ {profile.frequency}Hz
                 </div>
-                <Button variant="ghost" size="sm" onClick={() => onDelete(profile.processName)} className="text-destructive hover:text-destructive">
+                <Button variant="ghost" size="sm" onClick={() => onDelete(profile.index)} className="text-destructive hover:text-destructive">
                     <Trash2 className="h-4 w-4" />
                 </Button>
             </div>
@@ -73,7 +79,7 @@ function SortableProfileItem({ profile, onDelete }: { profile: ResolutionProfile
 }
 
 function ProfilesTab() {
-    const [profiles, setProfiles] = useState<ResolutionProfile[]>([])
+    const [profiles, setProfiles] = useState<ProfileRow[]>([])
     const [processes, setProcesses] = useState<ProcessInfo[]>([])
     const [resolutions, setResolutions] = useState<Resolution[]>([])
 
@@ -82,6 +88,7 @@ function ProfilesTab() {
     const [defaultProfileValue, setDefaultProfileValue] = useState<{ resolution: string; refreshRate: string } | null>(null)
 
     const [loading, setLoading] = useState(false)
+    const [saveError, setSaveError] = useState<string | null>(null)
 
     const sensors = useSensors(
         useSensor(PointerSensor),
@@ -100,11 +107,12 @@ function ProfilesTab() {
             const config = await getConfig()
 
             // Already an array now
-            const profileList: ResolutionProfile[] = config.automation.autoRes.profiles.map(p => ({
+            const profileList: ProfileRow[] = config.automation.autoRes.profiles.map((p, index) => ({
                 processName: p.process, // Mapped from process -> processName for local usage
                 width: p.width,
                 height: p.height,
-                frequency: p.frequency
+                frequency: p.frequency,
+                index
             }))
 
             setProfiles(profileList)
@@ -148,6 +156,19 @@ function ProfilesTab() {
         }
     }
 
+    // save_config rejects invalid matchers or profiles; show why instead of failing silently
+    const trySave = async (config: Config) => {
+        try {
+            await saveConfig(config)
+            setSaveError(null)
+            return true
+        } catch (e) {
+            console.error("Failed to save profiles", e)
+            setSaveError(String(e))
+            return false
+        }
+    }
+
     const handleAddProfile = async () => {
         if (!selectedProcess || !resPickerValue) return
 
@@ -157,15 +178,16 @@ function ProfilesTab() {
         const config = await getConfig();
         // Check if exists, replace or add
         const existingIdx = config.automation.autoRes.profiles.findIndex(p => p.process === selectedProcess);
-        const newEntry = { process: selectedProcess, width: w, height: h, frequency: freq };
 
         if (existingIdx >= 0) {
-            config.automation.autoRes.profiles[existingIdx] = newEntry;
+            // Only the mode is edited here; keep the rest of the profile
+            const existing = config.automation.autoRes.profiles[existingIdx]
+            config.automation.autoRes.profiles[existingIdx] = { ...existing, width: w, height: h, frequency: freq };
         } else {
-            config.automation.autoRes.profiles.push(newEntry);
+            config.automation.autoRes.profiles.push({ process: selectedProcess, width: w, height: h, frequency: freq });
         }
 
-        await saveConfig(config)
+        if (!await trySave(config)) return
         // Reload to sync state exactly
         await loadData(); // Simplified state sync
 
@@ -173,10 +195,10 @@ function ProfilesTab() {
         setResPickerValue(null)
     }
 
-    const handleDeleteProfile = async (processName: string) => {
+    const handleDeleteProfile = async (index: number) => {
         const config = await getConfig();
-        config.automation.autoRes.profiles = config.automation.autoRes.profiles.filter(p => p.process !== processName);
-        await saveConfig(config)
+        config.automation.autoRes.profiles = config.automation.autoRes.profiles.filter((_, i) => i !== index);
+        await trySave(config)
 
         await loadData();
     }
@@ -186,21 +208,18 @@ function ProfilesTab() {
 
         if (active.id !== over?.id) {
             setProfiles((items) => {
-                const oldIndex = items.findIndex((item) => item.processName === active.id)
-                const newIndex = items.findIndex((item) => item.processName === over?.id)
+                const oldIndex = items.findIndex((item) => rowId(item) === active.id)
+                const newIndex = items.findIndex((item) => rowId(item) === over?.id)
                 const newItems = arrayMove(items, oldIndex, newIndex)
 
                 // Persist new order!
-                // We need to map back to config structure
+                // Move the saved profiles themselves so fields not shown here survive
                 const saveOrder = async () => {
                     const config = await getConfig();
-                    config.automation.autoRes.profiles = newItems.map(p => ({
-                        process: p.processName,
-                        width: p.width,
-                        height: p.height,
-                        frequency: p.frequency
-                    }));
-                    await saveConfig(config);
+                    const saved = config.automation.autoRes.profiles
+                    config.automation.autoRes.profiles = newItems.map(p => saved[p.index]);
+                    await trySave(config);
+                    await loadData();
                 };
                 saveOrder();
 
@@ -220,7 +239,7 @@ function ProfilesTab() {
         } else {
             config.automation.autoRes.defaultProfile = undefined;
         }
-        await saveConfig(config)
+        await trySave(config)
     }
 
     const [revertUnit, setRevertUnit] = useState<"s" | "m">("s")
@@ -236,7 +255,7 @@ function ProfilesTab() {
 
         const config = await getConfig();
         config.automation.autoRes.revertDelay = totalMs;
-        await saveConfig(config);
+        await trySave(config);
     }
 
     return (
@@ -276,6 +295,7 @@ function ProfilesTab() {
                             <CardDescription>
                                 Priority is top to bottom. The first matching process determines the resolution.
                             </CardDescription>
+                            {saveError && <p className="text-sm text-destructive">Could not save: {saveError}</p>}
                         </div>
                         <Button variant="ghost" size="sm" onClick={loadData} disabled={loading}>
                             <RefreshCw className={`h-4 w-4 ${loading ? 'animate-spin' : ''}`} />
@@ -292,13 +312,13 @@ function ProfilesTab() {
                             onDragEnd={handleDragEnd}
                         >
                             <SortableContext
-                                items={profiles.map(p => p.processName)}
+                                items={profiles.map(rowId)}
                                 strategy={verticalListSortingStrategy}
                             >
                                 <div className="space-y-1">
                                     {profiles.map((profile) => (
                                         <SortableProfileItem
-                                            key={profile.processName}
+                                            key={rowId(profile)}
                                             profile={profile}
                                             onDelete={handleDeleteProfile}
                                         />